@@ -0,0 +1,101 @@
+//! Times `process_orders` on a large synthetic market.
+//!
+//! Run with `cargo bench --bench process_orders`. The app is a binary crate,
+//! so the order pipeline is compiled into the benchmark directly.
+
+#![allow(dead_code, unused_imports)]
+
+#[path = "../src/external_lib/mod.rs"]
+mod external_lib;
+
+use external_lib::filter::FavouriteSellers;
+use external_lib::pricing::FixedPrice;
+use external_lib::{Order, ScoreWeights, SortMode, User};
+use rand::Rng;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+const ORDERS: usize = 100_000;
+const ITEMS: usize = 500;
+const SELLERS: usize = 20_000;
+const RUNS: u32 = 10;
+
+/// Orders spread over `ITEMS` items and `SELLERS` sellers, sharing strings
+/// and users like fetched orders do.
+fn synthetic_orders() -> Vec<Order> {
+    let mut rng = rand::rng();
+    let users: Vec<Arc<User>> = (0..SELLERS)
+        .map(|i| {
+            Arc::new(User {
+                id: format!("user{i}"),
+                ingame_name: format!("Seller{i}"),
+                status: if rng.random_bool(0.7) { "ingame" } else { "offline" }.to_string(),
+                reputation: rng.random_range(0..100),
+                ..Default::default()
+            })
+        })
+        .collect();
+    let items: Vec<(Arc<str>, Arc<str>)> = (0..ITEMS)
+        .map(|i| (Arc::from(format!("Item {i} Prime Blueprint")), Arc::from(format!("item_{i}_prime_blueprint"))))
+        .collect();
+
+    let (sell, pc, en): (Arc<str>, Arc<str>, Arc<str>) = ("sell".into(), "pc".into(), "en".into());
+
+    (0..ORDERS)
+        .map(|i| {
+            let (item_name, item_url) = &items[i % ITEMS];
+            Order {
+                id: format!("order{i}"),
+                platinum: rng.random_range(1..=30),
+                quantity: rng.random_range(1..=20),
+                order_type: sell.clone(),
+                platform: pc.clone(),
+                region: en.clone(),
+                visible: true,
+                user: users[rng.random_range(0..SELLERS)].clone(),
+                item_name: Some(item_name.clone()),
+                item_url: Some(item_url.clone()),
+                ducats: Some(45),
+                trading_tax: Some(2_000),
+                ..Default::default()
+            }
+        })
+        .collect()
+}
+
+fn main() {
+    let orders = synthetic_orders();
+    let pricing = FixedPrice(3);
+
+    for sort_mode in [SortMode::DealScore, SortMode::TotalQuantity] {
+        let mut total = Duration::ZERO;
+        let mut slowest = Duration::ZERO;
+        let mut processed = 0;
+        for _ in 0..RUNS {
+            let start = Instant::now();
+            processed = external_lib::process_orders(
+                &orders,
+                external_lib::default_order_filter(
+                    external_lib::MAX_PRICE_TO_SEARCH,
+                    external_lib::MIN_QUANTITY_TO_SEARCH,
+                ),
+                &pricing,
+                sort_mode,
+                &ScoreWeights::default(),
+                &FavouriteSellers(Vec::new()),
+            )
+            .len();
+            let elapsed = start.elapsed();
+            total += elapsed;
+            slowest = slowest.max(elapsed);
+        }
+        println!(
+            "process_orders, {} orders -> {} kept, {:?}: mean {:?}, slowest {:?}",
+            ORDERS,
+            processed,
+            sort_mode,
+            total / RUNS,
+            slowest,
+        );
+    }
+}
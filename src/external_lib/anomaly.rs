@@ -0,0 +1,249 @@
+use crate::external_lib::Order;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+/// Quantities above this are treated as bait rather than real stock.
+pub const MAX_REALISTIC_QUANTITY: u32 = 100;
+
+/// Sell orders an item needs before prices are compared against each other.
+const MIN_ORDERS_FOR_PRICE_RANGE: usize = 5;
+
+/// Different items a seller needs listed at nearly the same price and quantity
+/// to be flagged. Single items are left out since many honest sellers list
+/// parts one at a time.
+const MIN_DUPLICATE_LISTINGS: usize = 4;
+
+/// How far, in percent of the larger value, prices and quantities of
+/// near-identical listings may differ.
+const DUPLICATE_TOLERANCE_PERCENT: u32 = 10;
+
+/// Why an order looks like bait.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Anomaly {
+    UnrealisticQuantity,
+    PriceOutlier,
+    DuplicateListings,
+}
+
+impl Anomaly {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Anomaly::UnrealisticQuantity => "Unrealistic quantity",
+            Anomaly::PriceOutlier => "Price outlier",
+            Anomaly::DuplicateListings => "Duplicate listings",
+        }
+    }
+
+    pub fn description(&self) -> String {
+        match self {
+            Anomaly::UnrealisticQuantity => {
+                format!("Lists more than {} items", MAX_REALISTIC_QUANTITY)
+            }
+            Anomaly::PriceOutlier => "Price is far above the item's normal range, or far below it \
+                 with an unrealistic quantity"
+                .to_string(),
+            Anomaly::DuplicateListings => format!(
+                "Seller lists {} or more items at nearly the same price and quantity",
+                MIN_DUPLICATE_LISTINGS
+            ),
+        }
+    }
+}
+
+/// Finds why each order looks like bait, by index into `orders`. Prices are
+/// compared with the other visible sell orders of the same item. A low price
+/// alone is what this app is looking for, so it only counts as an outlier
+/// together with an unrealistic quantity.
+pub fn detect_anomalies(orders: &[Order]) -> Vec<Vec<Anomaly>> {
+    let price_ranges = normal_price_ranges(orders);
+    let is_duplicate = duplicate_listings(orders);
+
+    orders
+        .iter()
+        .zip(is_duplicate)
+        .map(|(order, is_duplicate)| {
+            let mut anomalies = Vec::new();
+            let is_unrealistic_quantity = order.quantity > MAX_REALISTIC_QUANTITY;
+            if is_unrealistic_quantity {
+                anomalies.push(Anomaly::UnrealisticQuantity);
+            }
+            if let Some((low, high)) = order.item_url.as_deref().and_then(|url| price_ranges.get(url))
+                && (f64::from(order.platinum) > *high
+                    || (is_unrealistic_quantity && f64::from(order.platinum) < *low))
+            {
+                anomalies.push(Anomaly::PriceOutlier);
+            }
+            if is_duplicate {
+                anomalies.push(Anomaly::DuplicateListings);
+            }
+            anomalies
+        })
+        .collect()
+}
+
+/// Whether each sell order is one of a seller's near-identical listings
+/// across `MIN_DUPLICATE_LISTINGS` or more different items. Ranks and
+/// subtypes of one item count as a single item.
+fn duplicate_listings(orders: &[Order]) -> Vec<bool> {
+    let mut listings_by_seller: HashMap<&str, Vec<&Order>> = HashMap::new();
+    for order in orders {
+        if &*order.order_type == "sell" && order.quantity > 1 {
+            listings_by_seller
+                .entry(order.user.ingame_name.as_str())
+                .or_default()
+                .push(order);
+        }
+    }
+
+    let mut duplicate_ids: HashSet<&str> = HashSet::new();
+    for listings in listings_by_seller.values() {
+        if listings.len() < MIN_DUPLICATE_LISTINGS {
+            continue;
+        }
+        for order in listings {
+            let items: HashSet<Option<&str>> = listings
+                .iter()
+                .filter(|other| {
+                    is_near(other.platinum, order.platinum) && is_near(other.quantity, order.quantity)
+                })
+                .map(|other| other.item_url.as_deref())
+                .collect();
+            if items.len() >= MIN_DUPLICATE_LISTINGS {
+                duplicate_ids.insert(&order.id);
+            }
+        }
+    }
+
+    orders
+        .iter()
+        .map(|order| duplicate_ids.contains(order.id.as_str()))
+        .collect()
+}
+
+/// Whether two values differ by at most `DUPLICATE_TOLERANCE_PERCENT` of the
+/// larger one.
+fn is_near(a: u32, b: u32) -> bool {
+    u64::from(a.abs_diff(b)) * 100 <= u64::from(a.max(b)) * u64::from(DUPLICATE_TOLERANCE_PERCENT)
+}
+
+/// Tukey fences (1.5 × IQR beyond the quartiles) of each item's sell prices.
+/// Items where most sellers ask the same price have no spread to judge
+/// outliers by and are left out.
+fn normal_price_ranges(orders: &[Order]) -> HashMap<&str, (f64, f64)> {
+    let mut prices: HashMap<&str, Vec<u32>> = HashMap::new();
+    for order in orders {
+        if let Some(item_url) = &order.item_url
+            && &*order.order_type == "sell"
+            && order.visible
+        {
+            prices.entry(item_url).or_default().push(order.platinum);
+        }
+    }
+
+    prices
+        .into_iter()
+        .filter(|(_, prices)| prices.len() >= MIN_ORDERS_FOR_PRICE_RANGE)
+        .filter_map(|(item_url, mut prices)| {
+            prices.sort_unstable();
+            let q1 = quantile(&prices, 0.25);
+            let q3 = quantile(&prices, 0.75);
+            let iqr = q3 - q1;
+            (iqr > 0.0).then_some((item_url, (q1 - 1.5 * iqr, q3 + 1.5 * iqr)))
+        })
+        .collect()
+}
+
+/// Linearly interpolated quantile of sorted values.
+fn quantile(sorted: &[u32], q: f64) -> f64 {
+    let position = (sorted.len() - 1) as f64 * q;
+    let lower = position.floor() as usize;
+    let upper = position.ceil() as usize;
+    let fraction = position - lower as f64;
+    f64::from(sorted[lower]) * (1.0 - fraction) + f64::from(sorted[upper]) * fraction
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::external_lib::User;
+    use std::sync::Arc;
+
+    fn seller(name: &str) -> Arc<User> {
+        Arc::new(User {
+            ingame_name: name.to_string(),
+            ..Default::default()
+        })
+    }
+
+    fn order(id: &str, user: &Arc<User>, item_url: &str, platinum: u32, quantity: u32) -> Order {
+        Order {
+            id: id.to_string(),
+            platinum,
+            quantity,
+            order_type: "sell".into(),
+            visible: true,
+            user: user.clone(),
+            item_url: Some(item_url.into()),
+            ..Default::default()
+        }
+    }
+
+    fn has(anomalies: &[Anomaly], anomaly: Anomaly) -> bool {
+        anomalies.contains(&anomaly)
+    }
+
+    #[test]
+    fn same_price_and_quantity_across_items_is_flagged() {
+        let bait = seller("Bait");
+        let orders: Vec<Order> = (0..6)
+            .map(|i| order(&format!("o{i}"), &bait, &format!("item_{i}"), 1, 999))
+            .collect();
+        let anomalies = detect_anomalies(&orders);
+        assert!(anomalies.iter().all(|a| has(a, Anomaly::DuplicateListings)));
+        assert!(anomalies.iter().all(|a| has(a, Anomaly::UnrealisticQuantity)));
+    }
+
+    #[test]
+    fn near_identical_listings_are_flagged() {
+        let bait = seller("Bait");
+        let orders = vec![
+            order("a", &bait, "item_a", 20, 50),
+            order("b", &bait, "item_b", 21, 48),
+            order("c", &bait, "item_c", 19, 52),
+            order("d", &bait, "item_d", 20, 47),
+            order("e", &bait, "item_e", 40, 50),
+        ];
+        let anomalies = detect_anomalies(&orders);
+        assert!(anomalies[..4].iter().all(|a| has(a, Anomaly::DuplicateListings)));
+        assert!(!has(&anomalies[4], Anomaly::DuplicateListings));
+    }
+
+    #[test]
+    fn few_items_or_single_items_are_not_flagged() {
+        let honest = seller("Honest");
+        let orders = vec![
+            order("a", &honest, "item_a", 3, 5),
+            order("b", &honest, "item_b", 3, 5),
+            order("c", &honest, "item_c", 3, 5),
+            order("d", &honest, "item_d", 3, 1),
+            order("e", &honest, "item_e", 3, 1),
+        ];
+        let anomalies = detect_anomalies(&orders);
+        assert!(anomalies.iter().all(|a| !has(a, Anomaly::DuplicateListings)));
+    }
+
+    #[test]
+    fn ranks_of_one_item_and_other_sellers_do_not_count() {
+        let first = seller("First");
+        let second = seller("Second");
+        let orders = vec![
+            order("a", &first, "item_a", 3, 5),
+            order("b", &first, "item_a", 3, 5),
+            order("c", &first, "item_b", 3, 5),
+            order("d", &first, "item_c", 3, 5),
+            order("e", &second, "item_d", 3, 5),
+        ];
+        let anomalies = detect_anomalies(&orders);
+        assert!(anomalies.iter().all(|a| !has(a, Anomaly::DuplicateListings)));
+    }
+}
@@ -0,0 +1,96 @@
+use crate::external_lib::Order;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+/// What happened to an order between two fetches.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum OrderLifecycle {
+    New,
+    Unchanged,
+    PriceDropped,
+    PriceRaised,
+    QuantityChanged,
+    /// The order was delisted; the seller probably sold to someone else.
+    Gone,
+}
+
+impl OrderLifecycle {
+    pub fn label(&self) -> &'static str {
+        match self {
+            OrderLifecycle::New => "new",
+            OrderLifecycle::Unchanged => "unchanged",
+            OrderLifecycle::PriceDropped => "price dropped",
+            OrderLifecycle::PriceRaised => "price raised",
+            OrderLifecycle::QuantityChanged => "quantity changed",
+            OrderLifecycle::Gone => "gone",
+        }
+    }
+}
+
+/// Lifecycle states of orders keyed by `Order::id`.
+#[derive(Debug, Default, Clone)]
+pub struct OrderDiff {
+    pub states: HashMap<String, OrderLifecycle>,
+}
+
+impl OrderDiff {
+    pub fn state(&self, order_id: &str) -> Option<OrderLifecycle> {
+        self.states.get(order_id).copied()
+    }
+
+    pub fn count(&self, state: OrderLifecycle) -> usize {
+        self.states.values().filter(|s| **s == state).count()
+    }
+
+    /// IDs of orders in the given state.
+    pub fn ids(&self, state: OrderLifecycle) -> impl Iterator<Item = &str> {
+        self.states
+            .iter()
+            .filter(move |(_, s)| **s == state)
+            .map(|(id, _)| id.as_str())
+    }
+
+    /// Stores the diffed state on every order that appears in the diff.
+    pub fn apply(&self, orders: &mut [Order]) {
+        for order in orders {
+            if let Some(state) = self.state(&order.id) {
+                order.lifecycle = Some(state);
+            }
+        }
+    }
+}
+
+/// Compares two fetches of orders.
+///
+/// Orders are only reported as gone when their item was fetched again, so
+/// removing an item from the search list doesn't mark all its orders gone.
+pub fn diff_orders(previous: &[Order], current: &[Order]) -> OrderDiff {
+    let previous_by_id: HashMap<&str, &Order> =
+        previous.iter().map(|o| (o.id.as_str(), o)).collect();
+    let current_ids: HashSet<&str> = current.iter().map(|o| o.id.as_str()).collect();
+    let current_items: HashSet<Option<&str>> =
+        current.iter().map(|o| o.item_url.as_deref()).collect();
+
+    let mut states = HashMap::new();
+
+    for order in current {
+        let state = match previous_by_id.get(order.id.as_str()) {
+            None => OrderLifecycle::New,
+            Some(old) if order.platinum < old.platinum => OrderLifecycle::PriceDropped,
+            Some(old) if order.platinum > old.platinum => OrderLifecycle::PriceRaised,
+            Some(old) if order.quantity != old.quantity => OrderLifecycle::QuantityChanged,
+            Some(_) => OrderLifecycle::Unchanged,
+        };
+        states.insert(order.id.clone(), state);
+    }
+
+    for order in previous {
+        if !current_ids.contains(order.id.as_str())
+            && current_items.contains(&order.item_url.as_deref())
+        {
+            states.insert(order.id.clone(), OrderLifecycle::Gone);
+        }
+    }
+
+    OrderDiff { states }
+}
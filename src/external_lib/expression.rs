@@ -0,0 +1,610 @@
+use crate::external_lib::Order;
+use crate::external_lib::filter::OrderFilter;
+use std::fmt;
+
+/// Help text listing the supported expression syntax.
+pub const EXPRESSION_HELP: &str = "Fields: price, quantity, reputation, item, user, status, region, platform, type\n\
+Operators: == != < <= > >= and ~ (contains, text only)\n\
+Combine with AND, OR, NOT and parentheses. Text is case-insensitive.\n\
+Numbers may be negative, e.g. reputation > -5\n\
+Example: quantity >= 5 OR price <= 2\n\
+Example: NOT (item ~ \"Harrow\" AND price != 1)";
+
+/// How deeply `NOT` and parentheses may nest, so pasted expressions can't
+/// overflow the stack.
+const MAX_NESTING_DEPTH: usize = 64;
+
+/// An error in a filter expression, with the 0-based character position it was found at.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseError {
+    pub position: usize,
+    pub message: String,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "column {}: {}", self.position + 1, self.message)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// Parses a filter expression such as `quantity >= 5 OR price <= 2` into a filter.
+pub fn parse_filter_expression(input: &str) -> Result<Box<dyn OrderFilter>, ParseError> {
+    let tokens = tokenize(input)?;
+    let mut parser = Parser { tokens, index: 0, end: input.chars().count(), depth: 0 };
+    let filter = parser.parse_or()?;
+    match parser.peek() {
+        None => Ok(filter),
+        Some(token) => Err(ParseError {
+            position: token.position,
+            message: format!("unexpected {}", token.kind),
+        }),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum TokenKind {
+    Ident(String),
+    Number(i64),
+    Text(String),
+    Op(Op),
+    And,
+    Or,
+    Not,
+    LeftParen,
+    RightParen,
+}
+
+impl fmt::Display for TokenKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TokenKind::Ident(name) => write!(f, "'{}'", name),
+            TokenKind::Number(n) => write!(f, "number {}", n),
+            TokenKind::Text(text) => write!(f, "text \"{}\"", text),
+            TokenKind::Op(op) => write!(f, "operator '{}'", op.symbol()),
+            TokenKind::And => write!(f, "AND"),
+            TokenKind::Or => write!(f, "OR"),
+            TokenKind::Not => write!(f, "NOT"),
+            TokenKind::LeftParen => write!(f, "'('"),
+            TokenKind::RightParen => write!(f, "')'"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Token {
+    kind: TokenKind,
+    position: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Op {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Contains,
+}
+
+impl Op {
+    fn symbol(&self) -> &'static str {
+        match self {
+            Op::Eq => "==",
+            Op::Ne => "!=",
+            Op::Lt => "<",
+            Op::Le => "<=",
+            Op::Gt => ">",
+            Op::Ge => ">=",
+            Op::Contains => "~",
+        }
+    }
+}
+
+fn tokenize(input: &str) -> Result<Vec<Token>, ParseError> {
+    let chars: Vec<char> = input.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        let start = i;
+
+        if c.is_whitespace() {
+            i += 1;
+            continue;
+        }
+
+        let kind = match c {
+            '(' => {
+                i += 1;
+                TokenKind::LeftParen
+            }
+            ')' => {
+                i += 1;
+                TokenKind::RightParen
+            }
+            '~' => {
+                i += 1;
+                TokenKind::Op(Op::Contains)
+            }
+            '=' | '!' | '<' | '>' | '&' | '|' => {
+                let next = chars.get(i + 1).copied();
+                let (kind, len) = match (c, next) {
+                    ('=', Some('=')) => (TokenKind::Op(Op::Eq), 2),
+                    ('=', _) => (TokenKind::Op(Op::Eq), 1),
+                    ('!', Some('=')) => (TokenKind::Op(Op::Ne), 2),
+                    ('!', _) => (TokenKind::Not, 1),
+                    ('<', Some('=')) => (TokenKind::Op(Op::Le), 2),
+                    ('<', _) => (TokenKind::Op(Op::Lt), 1),
+                    ('>', Some('=')) => (TokenKind::Op(Op::Ge), 2),
+                    ('>', _) => (TokenKind::Op(Op::Gt), 1),
+                    ('&', Some('&')) => (TokenKind::And, 2),
+                    ('|', Some('|')) => (TokenKind::Or, 2),
+                    _ => {
+                        return Err(ParseError {
+                            position: start,
+                            message: format!("expected '{}{}'", c, c),
+                        });
+                    }
+                };
+                i += len;
+                kind
+            }
+            '"' | '\'' => {
+                let quote = c;
+                i += 1;
+                let text_start = i;
+                while i < chars.len() && chars[i] != quote {
+                    i += 1;
+                }
+                if i == chars.len() {
+                    return Err(ParseError {
+                        position: start,
+                        message: "unterminated text".to_string(),
+                    });
+                }
+                let text: String = chars[text_start..i].iter().collect();
+                i += 1;
+                TokenKind::Text(text)
+            }
+            c if c.is_ascii_digit()
+                || (c == '-' && chars.get(i + 1).is_some_and(|next| next.is_ascii_digit())) =>
+            {
+                i += 1;
+                while i < chars.len() && chars[i].is_ascii_digit() {
+                    i += 1;
+                }
+                let digits: String = chars[start..i].iter().collect();
+                let number = digits.parse().map_err(|_| ParseError {
+                    position: start,
+                    message: "number is too large".to_string(),
+                })?;
+                TokenKind::Number(number)
+            }
+            c if c.is_alphanumeric() || c == '_' => {
+                while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+                    i += 1;
+                }
+                let word: String = chars[start..i].iter().collect();
+                match word.to_ascii_uppercase().as_str() {
+                    "AND" => TokenKind::And,
+                    "OR" => TokenKind::Or,
+                    "NOT" => TokenKind::Not,
+                    _ => TokenKind::Ident(word),
+                }
+            }
+            c => {
+                return Err(ParseError {
+                    position: start,
+                    message: format!("unexpected character '{}'", c),
+                });
+            }
+        };
+
+        tokens.push(Token { kind, position: start });
+    }
+
+    Ok(tokens)
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Field {
+    Price,
+    Quantity,
+    Reputation,
+    Item,
+    User,
+    Status,
+    Region,
+    Platform,
+    OrderType,
+}
+
+impl Field {
+    fn from_name(name: &str) -> Option<Field> {
+        match name.to_ascii_lowercase().as_str() {
+            "price" | "platinum" => Some(Field::Price),
+            "quantity" => Some(Field::Quantity),
+            "reputation" => Some(Field::Reputation),
+            "item" => Some(Field::Item),
+            "user" | "seller" => Some(Field::User),
+            "status" => Some(Field::Status),
+            "region" => Some(Field::Region),
+            "platform" => Some(Field::Platform),
+            "type" => Some(Field::OrderType),
+            _ => None,
+        }
+    }
+
+    fn is_numeric(&self) -> bool {
+        matches!(self, Field::Price | Field::Quantity | Field::Reputation)
+    }
+
+    fn number(&self, order: &Order) -> i64 {
+        match self {
+            Field::Price => order.platinum.into(),
+            Field::Quantity => order.quantity.into(),
+            Field::Reputation => order.user.reputation,
+            _ => 0,
+        }
+    }
+
+    fn text<'a>(&self, order: &'a Order) -> &'a str {
+        match self {
+            Field::Item => order.item_name.as_deref().unwrap_or_default(),
+            Field::User => &order.user.ingame_name,
+            Field::Status => &order.user.status,
+            Field::Region => &order.region,
+            Field::Platform => &order.platform,
+            Field::OrderType => &order.order_type,
+            _ => "",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Value {
+    Number(i64),
+    Text(String),
+}
+
+/// A single `field op value` comparison.
+struct Comparison {
+    field: Field,
+    op: Op,
+    value: Value,
+}
+
+impl OrderFilter for Comparison {
+    fn matches(&self, order: &Order) -> bool {
+        match &self.value {
+            Value::Number(value) => {
+                let actual = self.field.number(order);
+                match self.op {
+                    Op::Eq => actual == *value,
+                    Op::Ne => actual != *value,
+                    Op::Lt => actual < *value,
+                    Op::Le => actual <= *value,
+                    Op::Gt => actual > *value,
+                    Op::Ge => actual >= *value,
+                    Op::Contains => false,
+                }
+            }
+            Value::Text(value) => {
+                let actual = self.field.text(order).to_lowercase();
+                match self.op {
+                    Op::Eq => actual == *value,
+                    Op::Ne => actual != *value,
+                    Op::Contains => actual.contains(value.as_str()),
+                    _ => false,
+                }
+            }
+        }
+    }
+}
+
+/// Operands of an `AND` chain. Kept flat so long chains don't nest.
+struct All(Vec<Box<dyn OrderFilter>>);
+
+impl OrderFilter for All {
+    fn matches(&self, order: &Order) -> bool {
+        self.0.iter().all(|filter| filter.matches(order))
+    }
+
+    fn needs_processing(&self) -> bool {
+        self.0.iter().any(|filter| filter.needs_processing())
+    }
+
+    fn prematches(&self, order: &Order) -> bool {
+        self.0.iter().all(|filter| filter.prematches(order))
+    }
+}
+
+/// Operands of an `OR` chain.
+struct Any(Vec<Box<dyn OrderFilter>>);
+
+impl OrderFilter for Any {
+    fn matches(&self, order: &Order) -> bool {
+        self.0.iter().any(|filter| filter.matches(order))
+    }
+
+    fn needs_processing(&self) -> bool {
+        self.0.iter().any(|filter| filter.needs_processing())
+    }
+
+    fn prematches(&self, order: &Order) -> bool {
+        self.0.iter().any(|filter| filter.prematches(order))
+    }
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    index: usize,
+    /// Position reported for errors at the end of input.
+    end: usize,
+    /// `NOT`s and parentheses currently being parsed.
+    depth: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.index)
+    }
+
+    fn next(&mut self) -> Result<Token, ParseError> {
+        let token = self.tokens.get(self.index).cloned().ok_or_else(|| ParseError {
+            position: self.end,
+            message: "unexpected end of expression".to_string(),
+        })?;
+        self.index += 1;
+        Ok(token)
+    }
+
+    fn eat(&mut self, kind: &TokenKind) -> bool {
+        if self.peek().is_some_and(|t| &t.kind == kind) {
+            self.index += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Runs `parse` one nesting level deeper. Fails past `MAX_NESTING_DEPTH`.
+    fn nested(
+        &mut self,
+        position: usize,
+        parse: impl FnOnce(&mut Self) -> Result<Box<dyn OrderFilter>, ParseError>,
+    ) -> Result<Box<dyn OrderFilter>, ParseError> {
+        if self.depth == MAX_NESTING_DEPTH {
+            return Err(ParseError {
+                position,
+                message: format!("nested more than {} levels deep", MAX_NESTING_DEPTH),
+            });
+        }
+        self.depth += 1;
+        let result = parse(self);
+        self.depth -= 1;
+        result
+    }
+
+    fn parse_or(&mut self) -> Result<Box<dyn OrderFilter>, ParseError> {
+        let mut filters = vec![self.parse_and()?];
+        while self.eat(&TokenKind::Or) {
+            filters.push(self.parse_and()?);
+        }
+        Ok(if filters.len() == 1 { filters.remove(0) } else { Box::new(Any(filters)) })
+    }
+
+    fn parse_and(&mut self) -> Result<Box<dyn OrderFilter>, ParseError> {
+        let mut filters = vec![self.parse_unary()?];
+        while self.eat(&TokenKind::And) {
+            filters.push(self.parse_unary()?);
+        }
+        Ok(if filters.len() == 1 { filters.remove(0) } else { Box::new(All(filters)) })
+    }
+
+    fn parse_unary(&mut self) -> Result<Box<dyn OrderFilter>, ParseError> {
+        if let Some(token) = self.peek()
+            && token.kind == TokenKind::Not
+        {
+            let position = token.position;
+            self.index += 1;
+            return self.nested(position, |parser| Ok(parser.parse_unary()?.not().boxed()));
+        }
+        self.parse_primary()
+    }
+
+    fn parse_primary(&mut self) -> Result<Box<dyn OrderFilter>, ParseError> {
+        let token = self.next()?;
+        match token.kind {
+            TokenKind::LeftParen => self.nested(token.position, |parser| {
+                let filter = parser.parse_or()?;
+                let close = parser.next().map_err(|e| ParseError {
+                    message: "missing ')'".to_string(),
+                    ..e
+                })?;
+                if close.kind != TokenKind::RightParen {
+                    return Err(ParseError {
+                        position: close.position,
+                        message: format!("expected ')', found {}", close.kind),
+                    });
+                }
+                Ok(filter)
+            }),
+            TokenKind::Ident(name) => {
+                let field = Field::from_name(&name).ok_or_else(|| ParseError {
+                    position: token.position,
+                    message: format!("unknown field '{}'", name),
+                })?;
+                self.parse_comparison(field)
+            }
+            kind => Err(ParseError {
+                position: token.position,
+                message: format!("expected a field or '(', found {}", kind),
+            }),
+        }
+    }
+
+    fn parse_comparison(&mut self, field: Field) -> Result<Box<dyn OrderFilter>, ParseError> {
+        let op_token = self.next()?;
+        let TokenKind::Op(op) = op_token.kind else {
+            return Err(ParseError {
+                position: op_token.position,
+                message: format!("expected a comparison operator, found {}", op_token.kind),
+            });
+        };
+
+        let value_token = self.next()?;
+        let value = match (field.is_numeric(), value_token.kind) {
+            (true, TokenKind::Number(n)) => Value::Number(n),
+            (false, TokenKind::Text(text) | TokenKind::Ident(text)) => {
+                Value::Text(text.to_lowercase())
+            }
+            (false, TokenKind::Number(n)) => Value::Text(n.to_string()),
+            (true, kind) => {
+                return Err(ParseError {
+                    position: value_token.position,
+                    message: format!("expected a number, found {}", kind),
+                });
+            }
+            (false, kind) => {
+                return Err(ParseError {
+                    position: value_token.position,
+                    message: format!("expected text, found {}", kind),
+                });
+            }
+        };
+
+        let is_op_supported = match value {
+            Value::Number(_) => op != Op::Contains,
+            Value::Text(_) => matches!(op, Op::Eq | Op::Ne | Op::Contains),
+        };
+        if !is_op_supported {
+            return Err(ParseError {
+                position: op_token.position,
+                message: format!("operator '{}' can't be used with this field", op.symbol()),
+            });
+        }
+
+        Ok(Box::new(Comparison { field, op, value }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::external_lib::User;
+    use std::sync::Arc;
+
+    fn order(platinum: u32, quantity: u32, region: &str) -> Order {
+        Order {
+            platinum,
+            quantity,
+            region: region.into(),
+            item_name: Some("Harrow Prime Blueprint".into()),
+            user: Arc::new(User {
+                ingame_name: "Seller".to_string(),
+                reputation: -3,
+                ..Default::default()
+            }),
+            ..Default::default()
+        }
+    }
+
+    fn matches(expression: &str, order: &Order) -> bool {
+        parse_filter_expression(expression).unwrap().matches(order)
+    }
+
+    fn error(expression: &str) -> ParseError {
+        match parse_filter_expression(expression) {
+            Ok(_) => panic!("{} should not parse", expression),
+            Err(err) => err,
+        }
+    }
+
+    #[test]
+    fn and_binds_tighter_than_or() {
+        // price <= 2 OR (quantity >= 5 AND region == ru)
+        let expression = "price <= 2 or quantity >= 5 and region == ru";
+        assert!(matches(expression, &order(1, 1, "en")));
+        assert!(!matches(expression, &order(3, 10, "en")));
+        assert!(matches(expression, &order(3, 10, "ru")));
+    }
+
+    #[test]
+    fn not_binds_tighter_than_and() {
+        // (NOT price == 1) AND quantity == 1
+        let expression = "not price == 1 and quantity == 1";
+        assert!(matches(expression, &order(2, 1, "en")));
+        assert!(!matches(expression, &order(1, 2, "en")));
+        assert!(matches("!!price == 1", &order(1, 1, "en")));
+    }
+
+    #[test]
+    fn parentheses_override_precedence() {
+        let expression = "(price <= 2 or quantity >= 5) and region == ru";
+        assert!(!matches(expression, &order(1, 1, "en")));
+        assert!(matches(expression, &order(1, 1, "ru")));
+        assert!(matches("not (price == 1 and quantity == 1)", &order(1, 2, "en")));
+    }
+
+    #[test]
+    fn text_is_case_insensitive() {
+        assert!(matches("item ~ 'HARROW'", &order(1, 1, "en")));
+        assert!(matches("user == seller", &order(1, 1, "en")));
+    }
+
+    #[test]
+    fn negative_numbers() {
+        assert!(matches("reputation > -5", &order(1, 1, "en")));
+        assert!(!matches("reputation >= -2", &order(1, 1, "en")));
+        assert_eq!(error("price == -").position, 9);
+    }
+
+    #[test]
+    fn unterminated_text() {
+        let err = error("item ~ \"Harrow");
+        assert_eq!(err.position, 7);
+        assert_eq!(err.message, "unterminated text");
+    }
+
+    #[test]
+    fn unknown_field() {
+        let err = error("price == 1 and colour == red");
+        assert_eq!(err.position, 15);
+        assert_eq!(err.message, "unknown field 'colour'");
+    }
+
+    #[test]
+    fn error_positions() {
+        assert_eq!(error("price == 1 )").position, 11);
+        assert_eq!(error("price ==").position, 8);
+        assert_eq!(error("(price == 1").message, "missing ')'");
+        assert_eq!(error("price ~ 1").position, 6);
+        assert_eq!(error("item < abc").position, 5);
+        assert_eq!(error("price == 1 & quantity == 2").position, 11);
+        assert_eq!(error("colour == red").to_string(), "column 1: unknown field 'colour'");
+    }
+
+    #[test]
+    fn nesting_is_limited() {
+        let depth = MAX_NESTING_DEPTH;
+        let nested = format!("{}price == 1{}", "(".repeat(depth), ")".repeat(depth));
+        assert!(matches(&nested, &order(1, 1, "en")));
+
+        let too_deep = format!("{}price == 1{}", "(".repeat(100_000), ")".repeat(100_000));
+        assert_eq!(error(&too_deep).position, depth);
+        assert_eq!(error(&"not ".repeat(100_000)).position, depth * 4);
+    }
+
+    #[test]
+    fn long_chains_do_not_nest() {
+        let chain = format!("{}price == 1", "price == 2 or quantity == 2 and ".repeat(100_000));
+        // The last term is `quantity == 2 and price == 1`
+        assert!(matches(&chain, &order(1, 2, "en")));
+        assert!(!matches(&chain, &order(1, 1, "en")));
+    }
+}
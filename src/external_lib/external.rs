@@ -1,9 +1,21 @@
+use crate::external_lib::OrderLifecycle;
+use crate::external_lib::Anomaly;
+use crate::external_lib::anomaly::detect_anomalies;
+use crate::external_lib::intern::share_order_fields;
+use crate::external_lib::pricing::PricingStrategy;
+use crate::external_lib::score::{DealScore, ScoreWeights, score_orders};
+use crate::external_lib::sorting::{SortMode, sort_seller_groups};
+use crate::external_lib::trades::{TradeLimits, split_into_trades};
+use crate::external_lib::tax::trading_tax;
+use crate::external_lib::filter::{
+    FavouriteSellers, OrderFilter, OrderType, Platform, PriceRange, QuantityRange, Status, Visible,
+};
+use chrono::{DateTime, Duration, Utc};
 use convert_case::{Case, Casing};
 use fake::{Fake, Faker};
 use serde::Deserialize;
 use serde::Serialize;
-use std::cmp;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::sync::Arc;
 use tokio::sync::Semaphore;
 use futures::stream::{FuturesUnordered, StreamExt};
@@ -11,7 +23,8 @@ use futures::stream::{FuturesUnordered, StreamExt};
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct GetOrdersResponse {
     pub payload: Payload,
-    // pub include: Include,
+    #[serde(default)]
+    pub include: Option<Include>,
 }
 
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -24,257 +37,256 @@ pub struct Order {
     pub id: String,
     pub platinum: u32,
     pub quantity: u32,
-    pub order_type: String,
-    // pub platform: String,
-    // pub region: String,
-    // pub creation_date: String,
-    // pub last_update: String,
-    // pub subtype: String,
+    /// Shared across orders like `user`, as there are only a few values.
+    pub order_type: Arc<str>,
+    // Orders saved before platform and region were modelled load with them empty
+    #[serde(default)]
+    pub platform: Arc<str>,
+    #[serde(default)]
+    pub region: Arc<str>,
+    // Orders saved before timestamps were parsed load with the Unix epoch,
+    // so they count as too old for the max age filter.
+    #[serde(default)]
+    pub creation_date: DateTime<Utc>,
+    #[serde(default)]
+    pub last_update: DateTime<Utc>,
+    #[serde(default)]
+    pub subtype: Option<String>,
+    #[serde(default)]
+    pub mod_rank: Option<u32>,
     pub visible: bool,
-    pub user: User,
-
-    #[serde(skip_deserializing)]
-    pub item_url: Option<String>,
-    #[serde(skip_deserializing)]
-    pub item_name: Option<String>,
-    #[serde(skip_deserializing)]
+    /// Shared by all of the seller's orders.
+    pub user: Arc<User>,
+
+    // Enrichment fields are filled by the app, not by the API. They default
+    // instead of being skipped so saved orders keep them on load.
+    #[serde(default)]
+    pub item_url: Option<Arc<str>>,
+    #[serde(default)]
+    pub item_name: Option<Arc<str>>,
+    /// Ducats one item converts to at Baro's kiosk, from the item metadata.
+    #[serde(default)]
+    pub ducats: Option<u32>,
+    #[serde(default)]
     pub price_to_offer: Option<u32>,
-    #[serde(skip_deserializing)]
+    #[serde(default)]
     pub sum_to_offer: Option<u32>,
-    #[serde(skip_deserializing)]
+    /// Ducats for the whole order.
+    #[serde(default)]
+    pub sum_ducats: Option<u32>,
+    /// Ducats per platinum at the offered price.
+    #[serde(default)]
+    pub ducats_per_platinum: Option<f32>,
+    /// Platinum to offer for all of this seller's processed orders.
+    #[serde(default)]
+    pub seller_sum_to_offer: Option<u32>,
+    /// Ducats for all of this seller's processed orders.
+    #[serde(default)]
+    pub seller_sum_ducats: Option<u32>,
+    #[serde(default)]
+    pub seller_ducats_per_platinum: Option<f32>,
+    /// Credits charged for trading one item, from the item metadata.
+    #[serde(default)]
+    pub trading_tax: Option<u32>,
+    /// Credits charged for trading the whole order.
+    #[serde(default)]
+    pub sum_trading_tax: Option<u64>,
+    /// Credits charged for trading all of this seller's processed orders.
+    #[serde(default)]
+    pub seller_sum_trading_tax: Option<u64>,
+    #[serde(default)]
     pub is_with_group: Option<bool>,
+    /// Whether the seller is a favourite, set by `process_orders`.
+    #[serde(default)]
+    pub is_favourite: Option<bool>,
+    #[serde(default)]
+    pub lifecycle: Option<OrderLifecycle>,
+    /// Reasons the order looks like bait. Empty if nothing is suspicious.
+    #[serde(default)]
+    pub anomalies: Vec<Anomaly>,
+    #[serde(default)]
+    pub deal_score: Option<DealScore>,
+
+    /// Any API fields not modelled above, kept so they survive save/export.
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
+}
+
+impl Order {
+    /// Time elapsed since the seller last updated this order.
+    pub fn age(&self, now: DateTime<Utc>) -> Duration {
+        now.signed_duration_since(self.last_update)
+    }
+}
+
+/// Formats an order age as a short human-readable string, e.g. `5m`, `3h`, `2d`.
+pub fn format_age(age: Duration) -> String {
+    if age.num_days() > 0 {
+        format!("{}d", age.num_days())
+    } else if age.num_hours() > 0 {
+        format!("{}h", age.num_hours())
+    } else {
+        format!("{}m", age.num_minutes().max(0))
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct User {
-    // pub id: String,
+    #[serde(default)]
+    pub id: String,
     pub ingame_name: String,
     pub status: String,
-    // pub region: String,
-    // pub reputation: i64,
-    // pub avatar: String,
-    // pub last_seen: String,
+    #[serde(default)]
+    pub region: String,
+    #[serde(default)]
+    pub reputation: i64,
+    #[serde(default)]
+    pub avatar: Option<String>,
+    #[serde(default)]
+    pub last_seen: Option<DateTime<Utc>>,
+
+    /// Any API fields not modelled above, kept so they survive save/export.
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
 }
 
 impl Default for User {
     fn default() -> Self {
         User {
+            id: Faker.fake(),
             ingame_name: Faker.fake(),
             status: Faker.fake(),
+            region: String::from("en"),
+            reputation: Faker.fake(),
+            avatar: None,
+            last_seen: None,
+            extra: serde_json::Map::new(),
         }
     }
 }
 
-// #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
-// pub struct Include {
-//     pub item: Item,
-// }
-
-// #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
-// pub struct Item {
-//     pub id: String,
-//     pub items_in_set: Vec<ItemsInSet>,
-// }
-
-// #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
-// pub struct ItemsInSet {
-//     pub id: String,
-//     pub url_name: String,
-//     pub icon: String,
-//     pub icon_format: String,
-//     pub thumb: String,
-//     pub sub_icon: String,
-//     pub mod_max_rank: i64,
-//     pub subtypes: Vec<String>,
-//     pub tags: Vec<String>,
-//     pub ducats: i64,
-//     pub quantity_for_set: i64,
-//     pub set_root: bool,
-//     pub mastery_level: i64,
-//     pub rarity: String,
-//     pub trading_tax: i64,
-//     pub en: En,
-//     pub ru: Ru,
-//     pub ko: Ko,
-//     pub fr: Fr,
-//     pub de: De,
-//     pub sv: Sv,
-//     pub zh_hant: ZhHant,
-//     pub zh_hans: ZhHans,
-//     pub pt: Pt,
-//     pub es: Es,
-//     pub pl: Pl,
-// }
-
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
-pub struct En {
-    pub item_name: String,
-    pub description: String,
-    pub wiki_link: String,
-    pub drop: Vec<Drop>,
+pub struct Include {
+    pub item: Item,
 }
 
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
-pub struct Drop {
-    pub name: String,
-    pub link: String,
-}
-
-#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
-pub struct Ru {
-    pub item_name: String,
-    pub description: String,
-    pub wiki_link: String,
-    pub drop: Vec<Drop2>,
-}
-
-#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
-pub struct Drop2 {
-    pub name: String,
-    pub link: String,
+pub struct Item {
+    pub id: String,
+    pub items_in_set: Vec<ItemsInSet>,
 }
 
-#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
-pub struct Ko {
-    pub item_name: String,
-    pub description: String,
-    pub wiki_link: String,
-    pub drop: Vec<Drop3>,
-}
+/// Languages item metadata is localized in.
+pub const LANGUAGES: [&str; 11] =
+    ["en", "ru", "ko", "fr", "de", "sv", "zh-hant", "zh-hans", "pt", "es", "pl"];
 
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
-pub struct Drop3 {
-    pub name: String,
-    pub link: String,
+#[serde(default)]
+pub struct ItemsInSet {
+    pub id: String,
+    pub url_name: String,
+    pub icon: String,
+    pub icon_format: Option<String>,
+    pub thumb: String,
+    pub sub_icon: Option<String>,
+    pub mod_max_rank: Option<i64>,
+    pub subtypes: Vec<String>,
+    pub tags: Vec<String>,
+    pub ducats: Option<u32>,
+    pub quantity_for_set: Option<u32>,
+    pub set_root: bool,
+    pub mastery_level: Option<i64>,
+    pub rarity: Option<String>,
+    pub trading_tax: Option<u32>,
+    /// Localized info keyed by language code (see [`LANGUAGES`]).
+    #[serde(flatten, deserialize_with = "deserialize_locales")]
+    pub locales: BTreeMap<String, ItemLocale>,
+}
+
+impl ItemsInSet {
+    /// Localized info in the given language, falling back to English.
+    pub fn locale(&self, language: &str) -> Option<&ItemLocale> {
+        self.locales.get(language).or_else(|| self.locales.get("en"))
+    }
 }
 
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
-pub struct Fr {
+#[serde(default)]
+pub struct ItemLocale {
     pub item_name: String,
     pub description: String,
-    pub wiki_link: String,
-    pub drop: Vec<Drop4>,
+    pub wiki_link: Option<String>,
+    pub drop: Vec<Drop>,
 }
 
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
-pub struct Drop4 {
+#[serde(default)]
+pub struct Drop {
     pub name: String,
-    pub link: String,
-}
-
-#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
-pub struct De {
-    pub item_name: String,
-    pub description: String,
-    pub wiki_link: String,
-    pub drop: Vec<Drop5>,
+    pub link: Option<String>,
+}
+
+/// Picks the language-keyed objects out of the flattened item fields.
+fn deserialize_locales<'de, D>(
+    deserializer: D,
+) -> Result<BTreeMap<String, ItemLocale>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let fields = serde_json::Map::<String, serde_json::Value>::deserialize(deserializer)?;
+    Ok(fields
+        .into_iter()
+        .filter(|(key, _)| LANGUAGES.contains(&key.as_str()))
+        .filter_map(|(key, value)| {
+            serde_json::from_value(value).ok().map(|locale| (key, locale))
+        })
+        .collect())
 }
 
-#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
-pub struct Drop5 {
-    pub name: String,
-    pub link: String,
-}
+const BASE_URL: &str = "https://api.warframe.market/v1";
 
-#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
-pub struct Sv {
-    pub item_name: String,
-    pub description: String,
-    pub wiki_link: String,
-    pub drop: Vec<Drop6>,
-}
+/// Platform the API lists orders for when no platform is requested.
+pub const PLATFORM: &str = "pc";
 
-#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
-pub struct Drop6 {
-    pub name: String,
-    pub link: String,
-}
+pub const MIN_QUANTITY_TO_SEARCH: u32 = 2;
+pub const MAX_PRICE_TO_SEARCH: u32 = 4;
 
-#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
-pub struct ZhHant {
-    pub item_name: String,
-    pub description: String,
-    pub wiki_link: String,
-    pub drop: Vec<Drop7>,
-}
+pub const PRICE_TO_OFFER: u32 = 3;
 
-#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
-pub struct Drop7 {
-    pub name: String,
-    pub link: String,
-}
+/// Default percentage of the asking price to offer.
+pub const OFFER_PERCENT: u32 = 75;
 
-#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
-pub struct ZhHans {
-    pub item_name: String,
-    pub description: String,
-    pub wiki_link: String,
-    pub drop: Vec<Drop8>,
-}
+/// Default ducats per platinum to aim for, e.g. 45 ducats for 3 platinum.
+pub const TARGET_DUCATS_PER_PLATINUM: u32 = 15;
 
-#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
-pub struct Drop8 {
-    pub name: String,
-    pub link: String,
-}
+/// Default quantity tiers as `min_quantity:price` pairs.
+pub const QUANTITY_TIERS: &str = "1:3, 10:2";
 
-#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
-pub struct Pt {
-    pub item_name: String,
-    pub description: String,
-    pub wiki_link: String,
-    pub drop: Vec<Drop9>,
-}
+/// Default platinum budget for the purchase optimizer.
+pub const PLATINUM_BUDGET: u32 = 150;
 
-#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
-pub struct Drop9 {
-    pub name: String,
-    pub link: String,
-}
+/// Default ducats to collect before Baro's visit.
+pub const DUCAT_TARGET: u32 = 1000;
 
-#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
-pub struct Es {
-    pub item_name: String,
-    pub description: String,
-    pub wiki_link: String,
-    pub drop: Vec<Drop10>,
-}
+/// Default estimate of minutes spent per trade, including whispering and inviting.
+pub const MINUTES_PER_TRADE: u32 = 3;
 
-#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
-pub struct Drop10 {
-    pub name: String,
-    pub link: String,
-}
+/// Orders not updated within this many hours are skipped. `0` disables the limit.
+pub const MAX_ORDER_AGE_HOURS: u32 = 24;
 
-#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
-pub struct Pl {
-    pub item_name: String,
-    pub description: String,
-    pub wiki_link: String,
-    pub drop: Vec<Drop11>,
-}
+/// Regions (chat languages) warframe.market orders can be listed in.
+pub const REGIONS: [&str; 12] =
+    ["en", "ru", "ko", "de", "fr", "pt", "zh", "es", "it", "pl", "uk", "sv"];
 
-#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
-pub struct Drop11 {
-    pub name: String,
-    pub link: String,
-}
-
-const BASE_URL: &str = "https://api.warframe.market/v1";
-
-pub const MIN_QUANTITY_TO_SEARCH: u32 = 2;
-pub const MAX_PRICE_TO_SEARCH: u32 = 4;
-
-pub const PRICE_TO_OFFER: u32 = 3;
-
-pub fn default_order_filter(order: &Order) -> bool {
-    let is_order_profitable = order.user.status == "ingame"
-        && order.order_type == "sell"
-        && order.visible
-        && order.platinum <= MAX_PRICE_TO_SEARCH
-        && order.quantity >= MIN_QUANTITY_TO_SEARCH;
-
-    is_order_profitable
+/// Visible sell orders on `PLATFORM` from sellers in game, priced at most
+/// `max_price` per item, with at least `min_quantity` items.
+pub fn default_order_filter(max_price: u32, min_quantity: u32) -> impl OrderFilter {
+    Status("ingame".to_string())
+        .and(OrderType("sell".to_string()))
+        .and(Visible)
+        .and(Platform(PLATFORM.to_string()))
+        .and(PriceRange(0..=max_price))
+        .and(QuantityRange(min_quantity..=u32::MAX))
 }
 
 pub const PROFITABLE_ITEM_NAMES: [&str; 34] = [
@@ -316,12 +328,20 @@ pub const PROFITABLE_ITEM_NAMES: [&str; 34] = [
 
 const DESIRED_PRICE: u32 = 3;
 
+/// Orders fetched for a set of items, together with the items' metadata.
+#[derive(Default, Debug, Clone)]
+pub struct FetchedOrders {
+    pub orders: Vec<Order>,
+    /// Item metadata keyed by `url_name`, including every part of each set.
+    pub items: HashMap<String, ItemsInSet>,
+}
+
 /// Fetches all orders for the given item names.
 pub async fn fetch_all_orders(
     item_names: &[String],
-) -> Result<Vec<Order>, Box<dyn std::error::Error + Send + Sync>> {
+) -> Result<FetchedOrders, Box<dyn std::error::Error + Send + Sync>> {
     let semaphore = Arc::new(Semaphore::new(3)); // Limit to 3 concurrent requests
-    let mut orders: Vec<Order> = Vec::new();
+    let mut fetched = FetchedOrders::default();
 
     let mut tasks = FuturesUnordered::new();
 
@@ -332,19 +352,34 @@ pub async fn fetch_all_orders(
         tasks.push(tokio::spawn(async move {
             let permit = semaphore.acquire_owned().await?; // Acquire a permit
             let item_url = item_name.to_case(Case::Snake);
-
-            // Fetch orders from the API
-            let response = reqwest::get(BASE_URL.to_owned() + "/items/" + &item_url + "/orders")
-                .await?;
+            // One copy of the item's name and URL, shared by all of its orders
+            let shared_item_name: Arc<str> = Arc::from(item_name.as_str());
+            let shared_item_url: Arc<str> = Arc::from(item_url.as_str());
+
+            // Fetch orders from the API, including the item metadata
+            let response = reqwest::get(
+                BASE_URL.to_owned() + "/items/" + &item_url + "/orders?include=item",
+            )
+            .await?;
             let get_orders_response = response.json::<GetOrdersResponse>().await?;
 
+            let items_in_set = get_orders_response
+                .include
+                .map(|include| include.item.items_in_set)
+                .unwrap_or_default();
+            let item = items_in_set.iter().find(|item| item.url_name == item_url);
+            let ducats = item.and_then(|item| item.ducats);
+            let trading_tax = item.and_then(trading_tax);
+
             let enriched_orders: Vec<Order> = get_orders_response
                 .payload
                 .orders
                 .into_iter()
                 .map(|mut order| {
-                    order.item_name = Some(item_name.to_string());
-                    order.item_url = Some(item_url.to_string());
+                    order.item_name = Some(shared_item_name.clone());
+                    order.item_url = Some(shared_item_url.clone());
+                    order.ducats = ducats;
+                    order.trading_tax = trading_tax;
                     order
                 })
                 .collect();
@@ -352,15 +387,18 @@ pub async fn fetch_all_orders(
             // Drop the permit when done
             drop(permit);
 
-            Ok::<Vec<Order>, Box<dyn std::error::Error + Send + Sync>>(enriched_orders)
+            Ok::<_, Box<dyn std::error::Error + Send + Sync>>((enriched_orders, items_in_set))
         }));
     }
 
     // Collect results as they finish
     while let Some(result) = tasks.next().await {
         match result {
-            Ok(Ok(mut enriched_orders)) => {
-                orders.append(&mut enriched_orders);
+            Ok(Ok((mut enriched_orders, items_in_set))) => {
+                fetched.orders.append(&mut enriched_orders);
+                for item in items_in_set {
+                    fetched.items.insert(item.url_name.clone(), item);
+                }
             }
             Ok(Err(err)) => {
                 eprintln!("Error fetching orders: {}", err);
@@ -371,92 +409,408 @@ pub async fn fetch_all_orders(
         }
     }
 
-    Ok(orders)
+    share_order_fields(&mut fetched.orders);
+    Ok(fetched)
 }
 
 /// Processes the orders by filtering, enriching fields, sorting.
-pub fn process_orders(orders: Vec<Order>,  filter: impl Fn(&Order) -> bool) -> Vec<Order> {
-    let mut grouped_orders: HashMap<String, Vec<Order>> = HashMap::new();
-
-    let filtered_orders: Vec<Order> = orders.into_iter().filter(|order| filter(order)).collect();
-
-    for mut order in filtered_orders {
-        order.is_with_group = Some(false);
-        order.price_to_offer = Some(cmp::min(PRICE_TO_OFFER, order.platinum));
-        order.sum_to_offer = Some(order.price_to_offer.unwrap() * order.quantity);
-        grouped_orders
-            .entry(order.user.ingame_name.clone())
-            .or_default()
-            .push(order);
-    }
-
-    // Mark orders with group
-    grouped_orders
-        .values_mut()
-        .filter(|orders| orders.len() > 1)
-        .for_each(|orders| {
-            for order in orders {
-                order.is_with_group = Some(true);
-            }
-        });
-
-    // Group by profit and sort
-    let mut processed_orders: Vec<_> = grouped_orders
-        .into_iter()
-        .map(|(user_name, mut orders)| {
-            orders.sort_by_key(|o| std::cmp::Reverse(o.quantity));
-            (user_name, orders)
+/// Orders from `favourites` are marked and their sellers ranked first.
+///
+/// Parts of the filter that don't need a price run on the borrowed orders
+/// first, so only orders that can still pass are copied. Copies are cheap
+/// because strings and users are shared, and sellers are grouped by index
+/// rather than by moving orders between collections.
+pub fn process_orders(
+    orders: &[Order],
+    filter: impl OrderFilter,
+    pricing: &dyn PricingStrategy,
+    sort_mode: SortMode,
+    score_weights: &ScoreWeights,
+    favourites: &FavouriteSellers,
+) -> Vec<Order> {
+    // Score against every fetched order, before filtering narrows the market down
+    let anomalies = detect_anomalies(orders);
+
+    // Price surviving orders before filtering again so filters can use the
+    // offer and ducat fields
+    let mut kept: Vec<Order> = orders
+        .iter()
+        .zip(anomalies)
+        .filter(|(order, _)| filter.prematches(order))
+        .filter_map(|(order, anomalies)| {
+            let mut order = order.clone();
+            let price_to_offer = pricing.price_to_offer(&order);
+            order.price_to_offer = Some(price_to_offer);
+            order.sum_to_offer = Some(price_to_offer * order.quantity);
+            order.sum_ducats = order.ducats.map(|ducats| ducats * order.quantity);
+            order.ducats_per_platinum = order.ducats.map(|ducats| ratio(ducats, price_to_offer));
+            order.sum_trading_tax = order.trading_tax.map(|tax| tax as u64 * order.quantity as u64);
+            order.anomalies = anomalies;
+            order.is_favourite = Some(favourites.matches(&order));
+            filter.matches(&order).then_some(order)
         })
         .collect();
 
-    processed_orders.sort_by_key(|(_, orders)| {
-        std::cmp::Reverse(orders.iter().map(|o| o.quantity).sum::<u32>())
-    });
+    // Group order indices by seller
+    let mut groups: Vec<Vec<usize>> = Vec::new();
+    let mut group_by_seller: HashMap<&str, usize> = HashMap::new();
+    for (index, order) in kept.iter().enumerate() {
+        let group = *group_by_seller
+            .entry(order.user.ingame_name.as_str())
+            .or_insert_with(|| {
+                groups.push(Vec::new());
+                groups.len() - 1
+            });
+        groups[group].push(index);
+    }
 
-    // Flatten to final list
-    processed_orders
-        .into_iter()
-        .flat_map(|(_, orders)| orders)
+    // Mark orders with group and store per-seller totals
+    for group in &groups {
+        let is_with_group = group.len() > 1;
+        let seller_sum_to_offer: u32 = group.iter().filter_map(|&i| kept[i].sum_to_offer).sum();
+        let seller_sum_ducats: u32 = group.iter().filter_map(|&i| kept[i].sum_ducats).sum();
+        let seller_sum_trading_tax: u64 =
+            group.iter().filter_map(|&i| kept[i].sum_trading_tax).sum();
+        for &index in group {
+            let order = &mut kept[index];
+            order.is_with_group = Some(is_with_group);
+            order.seller_sum_to_offer = Some(seller_sum_to_offer);
+            order.seller_sum_ducats = Some(seller_sum_ducats);
+            order.seller_ducats_per_platinum = Some(ratio(seller_sum_ducats, seller_sum_to_offer));
+            order.seller_sum_trading_tax = Some(seller_sum_trading_tax);
+        }
+    }
+
+    // Score deals, then sort sellers and their orders by the selected mode,
+    // favourites first
+    score_orders(&mut kept, &groups, score_weights);
+    sort_seller_groups(&kept, &mut groups, sort_mode);
+
+    // Move orders into their sorted positions
+    let mut slots: Vec<Option<Order>> = kept.into_iter().map(Some).collect();
+    groups
+        .iter()
+        .flatten()
+        .filter_map(|&index| slots[index].take())
         .collect()
 }
 
+/// Ducats per platinum, or 0 when nothing is paid.
+fn ratio(ducats: u32, platinum: u32) -> f32 {
+    if platinum == 0 {
+        0.0
+    } else {
+        ducats as f32 / platinum as f32
+    }
+}
+
 /// Generates a message for a single order.
-pub fn generate_message(order: &Order, desired_price: u32) -> String {
+///
+/// Processed orders keep the price `process_orders` computed; other orders are
+/// priced with the given strategy.
+/// Orders too big for one trade also say how many trades they take. Whispers
+/// longer than `MAX_WHISPER_LENGTH` lose their closing line, then have the
+/// item name shortened.
+pub fn generate_message(order: &Order, pricing: &dyn PricingStrategy, limits: TradeLimits) -> String {
     let user = &order.user.ingame_name;
     let platinum = order.platinum;
     let quantity = order.quantity;
-    let price_to_offer = cmp::min(desired_price, platinum);
+    let price_to_offer = order
+        .price_to_offer
+        .unwrap_or_else(|| pricing.price_to_offer(order));
     let total_price = price_to_offer * quantity;
     let item_name = order.item_name.as_ref().unwrap();
 
-    let linked_item_name = if let Some(stripped) = item_name.strip_suffix(" Blueprint") {
+    let is_offer_equal = price_to_offer == platinum;
+
+    let trades = split_into_trades(std::slice::from_ref(order), limits).len();
+    let total = if trades > 1 {
+        format!("Total: {total_price}:platinum: over {trades} trades")
+    } else {
+        format!("Total: {total_price}:platinum:")
+    };
+
+    let message = |linked_item_name: &str, closing: &str| {
+        if quantity == 1 && is_offer_equal {
+            format!(
+                "/w {user} Hi! I want to buy: {linked_item_name} for {platinum} platinum. (warframe.market)"
+            )
+        } else if is_offer_equal {
+            format!(
+                "/w {user} Hi! I want to buy all {quantity} of {linked_item_name} for {platinum}:platinum: each ({total})."
+            )
+        } else {
+            format!(
+                "/w {user} Hi! I want to buy all {quantity} of {linked_item_name}. I can offer {price_to_offer}:platinum: each ({total}). Your price was {platinum}:platinum: each.{closing}"
+            )
+        }
+    };
+
+    // Too long for the chat: drop the closing line, then shorten the item name
+    let linked = linked_item_name(item_name);
+    let full = message(&linked, " Let me know if you are interested!");
+    if char_count(&full) <= MAX_WHISPER_LENGTH {
+        return full;
+    }
+    let short = message(&linked, "");
+    let excess = char_count(&short).saturating_sub(MAX_WHISPER_LENGTH);
+    if excess == 0 {
+        short
+    } else {
+        let room = char_count(&linked).saturating_sub(excess);
+        message(&shorten_item(item_name, "", room), "")
+    }
+}
+
+
+/// Maximum length of a Warframe chat message in characters, including the
+/// `/w user ` prefix.
+pub const MAX_WHISPER_LENGTH: usize = 180;
+
+/// Links an item name in chat, keeping a `Blueprint` suffix outside the brackets.
+fn linked_item_name(item_name: &str) -> String {
+    if let Some(stripped) = item_name.strip_suffix(" Blueprint") {
         format!("[{}] Blueprint", stripped)
     } else {
         format!("[{}]", item_name)
+    }
+}
+
+/// Generates one whisper covering all of a seller's orders.
+///
+/// Returns several whispers when the items don't fit into one chat message;
+/// the total and the number of trades are only mentioned in the last one. An
+/// item too long for a whisper of its own has its name shortened.
+pub fn generate_combined_message(
+    orders: &[Order],
+    pricing: &dyn PricingStrategy,
+    limits: TradeLimits,
+) -> Vec<String> {
+    let Some(first) = orders.first() else {
+        return Vec::new();
     };
+    let user = &first.user.ingame_name;
 
-    let is_offer_equal = price_to_offer == platinum;
+    let mut total_price = 0;
+    let items: Vec<(&str, String)> = orders
+        .iter()
+        .map(|order| {
+            let price_to_offer = order
+                .price_to_offer
+                .unwrap_or_else(|| pricing.price_to_offer(order));
+            total_price += price_to_offer * order.quantity;
+            let item_name = order.item_name.as_deref().unwrap_or_default();
+            (item_name, format!(" x{} for {}p", order.quantity, price_to_offer))
+        })
+        .collect();
 
-    if quantity == 1 && is_offer_equal {
-        format!(
-            "/w {user} Hi! I want to buy: {linked_item_name} for {platinum} platinum. (warframe.market)"
-        )
-    } else if is_offer_equal {
-        format!(
-            "/w {user} Hi! I want to buy all {quantity} of {linked_item_name} for {platinum}:platinum: each (Total: {total_price}:platinum:)."
-        )
+    let prefix = format!("/w {user} Hi! I want to buy: ");
+    let continuation_prefix = format!("/w {user} Also: ");
+    let trades = split_into_trades(orders, limits).len();
+    let suffix = if trades > 1 {
+        format!(" (Total: {total_price}:platinum: over {trades} trades) (warframe.market)")
     } else {
-        format!(
-            "/w {user} Hi! I want to buy all {quantity} of {linked_item_name}. I can offer {price_to_offer}:platinum: each (Total: {total_price}:platinum:). Your price was {platinum}:platinum: each. Let me know if you are interested!"
-        )
+        format!(" (Total: {total_price}:platinum:) (warframe.market)")
+    };
+
+    let mut messages = Vec::new();
+    let mut current = prefix.clone();
+    let mut current_length = char_count(&current);
+    let mut current_items = 0;
+    for (i, (item_name, details)) in items.iter().enumerate() {
+        let is_last = i == items.len() - 1;
+        let tail_length = if is_last { char_count(&suffix) } else { 0 };
+        // Room for the item in a whisper of its own, with the longer prefix
+        let room = MAX_WHISPER_LENGTH.saturating_sub(char_count(&prefix) + tail_length);
+        let item = shorten_item(item_name, details, room);
+        let item_length = char_count(&item);
+        let separator_length = if current_items == 0 { 0 } else { 2 };
+        if current_items > 0
+            && current_length + separator_length + item_length + tail_length > MAX_WHISPER_LENGTH
+        {
+            messages.push(current);
+            current = continuation_prefix.clone();
+            current_length = char_count(&current);
+            current_items = 0;
+        }
+        if current_items > 0 {
+            current.push_str(", ");
+            current_length += 2;
+        }
+        current.push_str(&item);
+        current_length += item_length;
+        current_items += 1;
     }
+    current.push_str(&suffix);
+    messages.push(current);
+    messages
 }
 
+fn char_count(text: &str) -> usize {
+    text.chars().count()
+}
+
+/// Links an item name and adds its quantity and price, cutting the name short
+/// with `…` if the whole doesn't fit into `room` characters. Never returns
+/// more than `room` characters, dropping the link and then the details when
+/// even `[…]` doesn't fit beside them.
+fn shorten_item(item_name: &str, details: &str, room: usize) -> String {
+    let linked = linked_item_name(item_name);
+    let details_length = char_count(details);
+    if char_count(&linked) + details_length <= room {
+        return format!("{}{}", linked, details);
+    }
+    // Linking adds two brackets, and the ellipsis takes one more
+    if details_length + 3 <= room {
+        let shortened: String = item_name.chars().take(room - details_length - 3).collect();
+        return format!("[{}…]{}", shortened, details);
+    }
+    details.chars().take(room).collect()
+}
 
 /// Generates messages for all processed orders.
-pub fn generate_messages(orders: &[Order], desired_price: u32) -> Vec<String> {
+pub fn generate_messages(
+    orders: &[Order],
+    pricing: &dyn PricingStrategy,
+    limits: TradeLimits,
+) -> Vec<String> {
     orders
         .iter()
-        .map(|order| generate_message(order, desired_price))
+        .map(|order| generate_message(order, pricing, limits))
         .collect()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::external_lib::pricing::FixedPrice;
+
+    fn order(seller: &str, item_name: &str, platinum: u32, quantity: u32) -> Order {
+        Order {
+            id: format!("{seller}-{item_name}"),
+            platinum,
+            quantity,
+            item_name: Some(item_name.into()),
+            user: Arc::new(User {
+                ingame_name: seller.to_string(),
+                ..Default::default()
+            }),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn single_whisper_fits_in_chat() {
+        let long_name = "Titania Prime Neuroptics Blueprint ".repeat(4);
+        let order = order("AVeryLongSellerNameIndeed", long_name.trim(), 7, 40);
+        let limits = TradeLimits {
+            max_stacks_per_trade: 6,
+            max_items_per_stack: 1,
+        };
+        let message = generate_message(&order, &FixedPrice(3), limits);
+        assert!(char_count(&message) <= MAX_WHISPER_LENGTH, "{}", message);
+        assert!(message.contains("over 7 trades"), "{}", message);
+        assert!(message.contains("…]"), "{}", message);
+    }
+
+    fn seller_orders(seller: &str, item_names: &[&str]) -> Vec<Order> {
+        item_names
+            .iter()
+            .map(|item_name| order(seller, item_name, 4, 2))
+            .collect()
+    }
+
+    #[test]
+    fn combined_whispers_fit_in_chat() {
+        let item_names: Vec<String> =
+            (0..30).map(|i| format!("Braton Prime Receiver {i}")).collect();
+        let item_names: Vec<&str> = item_names.iter().map(String::as_str).collect();
+        let orders = seller_orders("Seller", &item_names);
+        let messages = generate_combined_message(&orders, &FixedPrice(3), TradeLimits::default());
+        assert!(messages.len() > 1);
+        for message in &messages {
+            assert!(char_count(message) <= MAX_WHISPER_LENGTH, "{}", message);
+        }
+    }
+
+    #[test]
+    fn combined_whispers_split_items_in_order() {
+        let item_names: Vec<String> =
+            (0..12).map(|i| format!("Knell Prime Receiver {i}")).collect();
+        let item_names: Vec<&str> = item_names.iter().map(String::as_str).collect();
+        let orders = seller_orders("Seller", &item_names);
+        let messages = generate_combined_message(&orders, &FixedPrice(3), TradeLimits::default());
+
+        assert!(messages[0].starts_with("/w Seller Hi! I want to buy: "));
+        for message in &messages[1..] {
+            assert!(message.starts_with("/w Seller Also: "), "{}", message);
+        }
+        // Every item appears once, in order, and only the last whisper has the total
+        let joined = messages.join(" ");
+        let mut position = 0;
+        for item_name in &item_names {
+            let found = joined[position..].find(&format!("[{item_name}] x2 for 3p"));
+            position += found.unwrap_or_else(|| panic!("{} missing or out of order", item_name));
+        }
+        // 12 items don't fit into the 6 slots of one trade
+        assert!(messages
+            .last()
+            .unwrap()
+            .ends_with("(Total: 72:platinum: over 2 trades) (warframe.market)"));
+        assert!(messages[..messages.len() - 1].iter().all(|m| !m.contains("Total")));
+    }
+
+    #[test]
+    fn very_long_item_names_are_shortened() {
+        let long_name = "Prime ".repeat(60);
+        let orders = seller_orders("Seller", &["Braton Prime Receiver", long_name.trim()]);
+        let messages = generate_combined_message(&orders, &FixedPrice(3), TradeLimits::default());
+        for message in &messages {
+            assert!(char_count(message) <= MAX_WHISPER_LENGTH, "{}", message);
+        }
+        assert!(messages.last().unwrap().contains("…] x2 for 3p"));
+    }
+
+    #[test]
+    fn shortened_items_never_exceed_room() {
+        let details = " x10 for 3p";
+        for room in 0..40 {
+            let item = shorten_item("Titania Prime Neuroptics Blueprint", details, room);
+            assert!(char_count(&item) <= room, "{} is longer than {}", item, room);
+        }
+        assert_eq!(shorten_item("Braton Prime Receiver", details, 16), "[Br…] x10 for 3p");
+        assert_eq!(shorten_item("Braton Prime Receiver", details, 5), " x10 ");
+    }
+
+    #[test]
+    fn orders_saved_without_timestamps_still_load() {
+        let order: Order = serde_json::from_str(
+            r#"{"id": "1", "platinum": 3, "quantity": 2, "order_type": "sell",
+                "platform": "pc", "region": "en", "visible": true,
+                "user": {"id": "u", "ingame_name": "Seller", "status": "ingame",
+                         "region": "en", "reputation": 1}}"#,
+        )
+        .unwrap();
+        assert_eq!(order.last_update, DateTime::<Utc>::default());
+    }
+
+    #[test]
+    fn orders_saved_without_platform_and_region_still_load() {
+        let order: Order = serde_json::from_str(
+            r#"{"id": "1", "platinum": 3, "quantity": 2, "order_type": "sell", "visible": true,
+                "user": {"ingame_name": "Seller", "status": "ingame"}}"#,
+        )
+        .unwrap();
+        assert_eq!(&*order.platform, "");
+        assert_eq!(&*order.region, "");
+        assert_eq!(order.user.reputation, 0);
+    }
+
+    #[test]
+    fn single_whisper_mentions_trades_only_when_split() {
+        let order = order("Seller", "Braton Prime Receiver", 4, 10);
+        let message = generate_message(&order, &FixedPrice(3), TradeLimits::default());
+        assert!(message.contains("(Total: 30:platinum:)"), "{}", message);
+        assert!(!message.contains("trades"), "{}", message);
+    }
+}
@@ -0,0 +1,361 @@
+use crate::external_lib::Order;
+use chrono::{DateTime, Duration, Utc};
+use std::collections::HashSet;
+use std::ops::RangeInclusive;
+
+/// Decides whether an order should be kept by `process_orders`.
+///
+/// Building blocks below can be composed with [`OrderFilter::and`],
+/// [`OrderFilter::or`] and [`OrderFilter::not`]. Plain closures taking
+/// `&Order` are filters too.
+pub trait OrderFilter: Send + Sync {
+    fn matches(&self, order: &Order) -> bool;
+
+    /// Whether the filter reads fields `process_orders` fills in, like the
+    /// offered price or anomalies.
+    fn needs_processing(&self) -> bool {
+        false
+    }
+
+    /// Checks an order before `process_orders` fills in its fields, so orders
+    /// that can't pass aren't copied. Parts needing processing pass for now.
+    fn prematches(&self, order: &Order) -> bool {
+        self.needs_processing() || self.matches(order)
+    }
+
+    fn and<F: OrderFilter>(self, other: F) -> And<Self, F>
+    where
+        Self: Sized,
+    {
+        And(self, other)
+    }
+
+    fn or<F: OrderFilter>(self, other: F) -> Or<Self, F>
+    where
+        Self: Sized,
+    {
+        Or(self, other)
+    }
+
+    fn not(self) -> Not<Self>
+    where
+        Self: Sized,
+    {
+        Not(self)
+    }
+
+    fn boxed(self) -> Box<dyn OrderFilter>
+    where
+        Self: Sized + 'static,
+    {
+        Box::new(self)
+    }
+}
+
+impl<F: Fn(&Order) -> bool + Send + Sync> OrderFilter for F {
+    fn matches(&self, order: &Order) -> bool {
+        self(order)
+    }
+
+    /// Closures may read anything, so they always run on processed orders.
+    fn needs_processing(&self) -> bool {
+        true
+    }
+}
+
+impl OrderFilter for Box<dyn OrderFilter> {
+    fn matches(&self, order: &Order) -> bool {
+        self.as_ref().matches(order)
+    }
+
+    fn needs_processing(&self) -> bool {
+        self.as_ref().needs_processing()
+    }
+
+    fn prematches(&self, order: &Order) -> bool {
+        self.as_ref().prematches(order)
+    }
+}
+
+pub struct And<A, B>(pub A, pub B);
+
+impl<A: OrderFilter, B: OrderFilter> OrderFilter for And<A, B> {
+    fn matches(&self, order: &Order) -> bool {
+        self.0.matches(order) && self.1.matches(order)
+    }
+
+    fn needs_processing(&self) -> bool {
+        self.0.needs_processing() || self.1.needs_processing()
+    }
+
+    fn prematches(&self, order: &Order) -> bool {
+        self.0.prematches(order) && self.1.prematches(order)
+    }
+}
+
+pub struct Or<A, B>(pub A, pub B);
+
+impl<A: OrderFilter, B: OrderFilter> OrderFilter for Or<A, B> {
+    fn matches(&self, order: &Order) -> bool {
+        self.0.matches(order) || self.1.matches(order)
+    }
+
+    fn needs_processing(&self) -> bool {
+        self.0.needs_processing() || self.1.needs_processing()
+    }
+
+    fn prematches(&self, order: &Order) -> bool {
+        self.0.prematches(order) || self.1.prematches(order)
+    }
+}
+
+pub struct Not<A>(pub A);
+
+impl<A: OrderFilter> OrderFilter for Not<A> {
+    fn matches(&self, order: &Order) -> bool {
+        !self.0.matches(order)
+    }
+
+    fn needs_processing(&self) -> bool {
+        self.0.needs_processing()
+    }
+}
+
+/// Keeps orders whose seller has the given status, e.g. `ingame`.
+pub struct Status(pub String);
+
+impl OrderFilter for Status {
+    fn matches(&self, order: &Order) -> bool {
+        order.user.status == self.0
+    }
+}
+
+/// Keeps orders that are visible on the market.
+pub struct Visible;
+
+impl OrderFilter for Visible {
+    fn matches(&self, order: &Order) -> bool {
+        order.visible
+    }
+}
+
+/// Keeps orders of the given type, `sell` or `buy`.
+pub struct OrderType(pub String);
+
+impl OrderFilter for OrderType {
+    fn matches(&self, order: &Order) -> bool {
+        *order.order_type == *self.0
+    }
+}
+
+/// Keeps orders priced within the range, in platinum per item.
+pub struct PriceRange(pub RangeInclusive<u32>);
+
+impl OrderFilter for PriceRange {
+    fn matches(&self, order: &Order) -> bool {
+        self.0.contains(&order.platinum)
+    }
+}
+
+/// Keeps orders whose quantity is within the range.
+pub struct QuantityRange(pub RangeInclusive<u32>);
+
+impl OrderFilter for QuantityRange {
+    fn matches(&self, order: &Order) -> bool {
+        self.0.contains(&order.quantity)
+    }
+}
+
+/// An in-game name pattern. `*` matches any run of characters and `?` any
+/// single character, so `Bait*` matches every name starting with `Bait`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct NamePattern {
+    pattern: Vec<char>,
+    case_sensitive: bool,
+}
+
+impl NamePattern {
+    pub fn new(pattern: &str, case_sensitive: bool) -> Self {
+        Self {
+            pattern: fold_case(pattern, case_sensitive),
+            case_sensitive,
+        }
+    }
+
+    pub fn matches(&self, name: &str) -> bool {
+        let name = fold_case(name, self.case_sensitive);
+        let (mut p, mut n) = (0, 0);
+        // Where the last `*` was seen, and the name position it matched up to.
+        let mut backtrack = None;
+        while n < name.len() {
+            match self.pattern.get(p) {
+                Some('*') => {
+                    backtrack = Some((p, n));
+                    p += 1;
+                }
+                Some(&c) if c == '?' || c == name[n] => {
+                    p += 1;
+                    n += 1;
+                }
+                _ => match backtrack {
+                    Some((star, matched)) => {
+                        backtrack = Some((star, matched + 1));
+                        p = star + 1;
+                        n = matched + 1;
+                    }
+                    None => return false,
+                },
+            }
+        }
+        self.pattern[p..].iter().all(|&c| c == '*')
+    }
+}
+
+fn fold_case(text: &str, case_sensitive: bool) -> Vec<char> {
+    if case_sensitive {
+        text.chars().collect()
+    } else {
+        text.chars().flat_map(char::to_lowercase).collect()
+    }
+}
+
+/// Rejects orders from sellers whose in-game name matches any of the patterns.
+pub struct UserBlocklist(pub Vec<NamePattern>);
+
+impl OrderFilter for UserBlocklist {
+    fn matches(&self, order: &Order) -> bool {
+        !self.0.iter().any(|pattern| pattern.matches(&order.user.ingame_name))
+    }
+}
+
+/// Keeps orders from favourite sellers, whose in-game name matches any of the patterns.
+pub struct FavouriteSellers(pub Vec<NamePattern>);
+
+impl OrderFilter for FavouriteSellers {
+    fn matches(&self, order: &Order) -> bool {
+        self.0.iter().any(|pattern| pattern.matches(&order.user.ingame_name))
+    }
+}
+
+/// Rejects orders whose IDs were already contacted.
+pub struct Contacted(pub HashSet<String>);
+
+impl OrderFilter for Contacted {
+    fn matches(&self, order: &Order) -> bool {
+        !self.0.contains(&order.id)
+    }
+}
+
+/// Keeps orders listed on the given platform, e.g. `pc`.
+pub struct Platform(pub String);
+
+impl OrderFilter for Platform {
+    fn matches(&self, order: &Order) -> bool {
+        *order.platform == *self.0
+    }
+}
+
+/// Keeps orders listed in any of the given regions. An empty set keeps all.
+pub struct Regions(pub HashSet<String>);
+
+impl OrderFilter for Regions {
+    fn matches(&self, order: &Order) -> bool {
+        self.0.is_empty() || self.0.contains(&*order.region)
+    }
+}
+
+/// Keeps orders updated within `max_age` of `now`.
+pub struct MaxAge {
+    pub max_age: Duration,
+    pub now: DateTime<Utc>,
+}
+
+impl OrderFilter for MaxAge {
+    fn matches(&self, order: &Order) -> bool {
+        order.age(self.now) <= self.max_age
+    }
+}
+
+/// Keeps orders yielding at least this many ducats per platinum at the offered
+/// price. Orders with an unknown ducat value are rejected.
+pub struct MinDucatsPerPlatinum(pub f32);
+
+impl OrderFilter for MinDucatsPerPlatinum {
+    fn matches(&self, order: &Order) -> bool {
+        order.ducats_per_platinum.is_some_and(|ratio| ratio >= self.0)
+    }
+
+    fn needs_processing(&self) -> bool {
+        true
+    }
+}
+
+/// Rejects orders flagged as possible bait.
+pub struct NoAnomalies;
+
+impl OrderFilter for NoAnomalies {
+    fn matches(&self, order: &Order) -> bool {
+        order.anomalies.is_empty()
+    }
+
+    fn needs_processing(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matches(pattern: &str, name: &str) -> bool {
+        NamePattern::new(pattern, false).matches(name)
+    }
+
+    #[test]
+    fn star_matches_any_run_of_characters() {
+        assert!(matches("Bait*", "Bait"));
+        assert!(matches("Bait*", "Bait123"));
+        assert!(matches("*Bait", "XxBait"));
+        assert!(matches("B*t", "Bt"));
+        assert!(matches("B*t", "Bait"));
+        assert!(matches("*", ""));
+        assert!(matches("**", "Anyone"));
+        assert!(!matches("Bait*", "NoBait"));
+    }
+
+    #[test]
+    fn star_backtracks_to_later_matches() {
+        assert!(matches("*ab*ab", "aabab"));
+        assert!(matches("a*b*c", "abbbbc"));
+        assert!(matches("*Seller*99", "TheSeller_Seller99"));
+        assert!(!matches("a*b*c", "abbbb"));
+    }
+
+    #[test]
+    fn question_mark_matches_one_character() {
+        assert!(matches("Seller?", "Seller1"));
+        assert!(!matches("Seller?", "Seller"));
+        assert!(!matches("Seller?", "Seller12"));
+        assert!(matches("?ait", "Bait"));
+        assert!(matches("S?ll?r", "Sellar"));
+    }
+
+    #[test]
+    fn patterns_match_the_whole_name() {
+        assert!(matches("Seller", "Seller"));
+        assert!(!matches("Seller", "Seller2"));
+        assert!(!matches("Seller", "TheSeller"));
+        assert!(!matches("eller", "Seller"));
+        assert!(!matches("", "Seller"));
+        assert!(matches("", ""));
+    }
+
+    #[test]
+    fn case_is_ignored_unless_requested() {
+        assert!(matches("bait*", "BAIT_Seller"));
+        assert!(matches("ÄRGER", "ärger"));
+        let case_sensitive = NamePattern::new("Bait*", true);
+        assert!(case_sensitive.matches("Bait1"));
+        assert!(!case_sensitive.matches("bait1"));
+    }
+}
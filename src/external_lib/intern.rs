@@ -0,0 +1,33 @@
+use crate::external_lib::{Order, User};
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+/// Makes orders share one copy of values the API repeats on every order. The
+/// seller is repeated on each of their orders across every fetched item, and
+/// order type, platform and region only take a handful of values.
+///
+/// Orders are expected in the order their items were fetched. A seller's
+/// status can change between items, so the last copy, the freshest, is kept.
+pub fn share_order_fields(orders: &mut [Order]) {
+    let mut users: HashMap<String, Arc<User>> = HashMap::new();
+    for order in orders.iter() {
+        users.insert(order.user.id.clone(), order.user.clone());
+    }
+    let mut strings: HashSet<Arc<str>> = HashSet::new();
+    for order in orders {
+        order.user = users[&order.user.id].clone();
+        for field in [&mut order.order_type, &mut order.platform, &mut order.region] {
+            *field = share_str(&mut strings, field);
+        }
+    }
+}
+
+fn share_str(strings: &mut HashSet<Arc<str>>, value: &Arc<str>) -> Arc<str> {
+    match strings.get(value) {
+        Some(shared) => shared.clone(),
+        None => {
+            strings.insert(value.clone());
+            value.clone()
+        }
+    }
+}
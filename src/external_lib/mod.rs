@@ -1,16 +1,65 @@
+pub mod anomaly;
+pub mod diff;
+pub mod expression;
 pub mod external;
+pub mod filter;
+pub mod intern;
 pub mod mock;
+pub mod optimizer;
+pub mod planner;
+pub mod pricing;
+pub mod score;
+pub mod sorting;
+pub mod tax;
+pub mod trades;
+pub mod worldstate;
 
+pub use anomaly::Anomaly;
+pub use diff::OrderLifecycle;
+pub use diff::diff_orders;
 pub use external::Order;
+pub use filter::OrderFilter;
+pub use pricing::PricingStrategy;
+pub use score::ScoreWeights;
+pub use sorting::SortMode;
+pub use tax::format_credits;
+pub use trades::TradeLimits;
+pub use trades::split_into_trades;
+pub use optimizer::OptimizationGoal;
+pub use optimizer::PurchasePlan;
+pub use optimizer::optimize_purchases;
+pub use planner::VisitPlan;
+pub use planner::plan_visit;
+pub use expression::EXPRESSION_HELP;
+pub use expression::ParseError;
+pub use expression::parse_filter_expression;
 pub use external::User;
+pub use external::FetchedOrders;
+pub use external::ItemsInSet;
+pub use external::LANGUAGES;
+pub use external::format_age;
 pub use external::default_order_filter;
 pub use external::PROFITABLE_ITEM_NAMES;
 pub use external::PRICE_TO_OFFER;
+pub use external::PLATINUM_BUDGET;
+pub use external::DUCAT_TARGET;
+pub use external::MINUTES_PER_TRADE;
+pub use external::OFFER_PERCENT;
+pub use external::TARGET_DUCATS_PER_PLATINUM;
+pub use external::QUANTITY_TIERS;
 pub use external::MIN_QUANTITY_TO_SEARCH;
 pub use external::MAX_PRICE_TO_SEARCH;
+pub use external::MAX_ORDER_AGE_HOURS;
+pub use external::REGIONS;
 
 
 pub use external::fetch_all_orders;
 pub use external::process_orders;
 pub use external::generate_message;
+pub use external::generate_combined_message;
 pub use external::generate_messages;
+
+pub use worldstate::VoidTrader;
+pub use worldstate::DEFAULT_WORLDSTATE_URL;
+pub use worldstate::fetch_void_trader;
+pub use worldstate::format_countdown;
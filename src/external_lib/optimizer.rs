@@ -0,0 +1,384 @@
+use crate::external_lib::Order;
+use crate::external_lib::trades::{TradeLimits, split_into_trades};
+use std::collections::HashMap;
+use serde::{Deserialize, Serialize};
+
+/// What the budget optimizer maximizes.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub enum OptimizationGoal {
+    #[default]
+    MaxDucats,
+    MaxTrades,
+}
+
+impl OptimizationGoal {
+    pub const ALL: [OptimizationGoal; 2] =
+        [OptimizationGoal::MaxDucats, OptimizationGoal::MaxTrades];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            OptimizationGoal::MaxDucats => "Most ducats",
+            OptimizationGoal::MaxTrades => "Most trades",
+        }
+    }
+}
+
+/// Part or all of an order chosen by the optimizer.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlannedPurchase {
+    pub order: Order,
+    pub quantity: u32,
+    pub platinum: u32,
+    pub ducats: u32,
+    /// Credits charged for trading the purchased items.
+    pub trading_tax: u64,
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct PurchasePlan {
+    pub purchases: Vec<PlannedPurchase>,
+    pub total_platinum: u32,
+    pub total_ducats: u32,
+    pub total_trading_tax: u64,
+}
+
+impl PurchasePlan {
+    pub(crate) fn from_quantities(orders: &[Order], quantities: &[u32]) -> Self {
+        let purchases: Vec<PlannedPurchase> = orders
+            .iter()
+            .zip(quantities)
+            .filter(|(_, quantity)| **quantity > 0)
+            .map(|(order, &quantity)| PlannedPurchase {
+                order: order.clone(),
+                quantity,
+                platinum: unit_price(order) * quantity,
+                ducats: order.ducats.unwrap_or_default() * quantity,
+                trading_tax: order.trading_tax.unwrap_or_default() as u64 * quantity as u64,
+            })
+            .collect();
+        PurchasePlan {
+            total_platinum: purchases.iter().map(|p| p.platinum).sum(),
+            total_ducats: purchases.iter().map(|p| p.ducats).sum(),
+            total_trading_tax: purchases.iter().map(|p| p.trading_tax).sum(),
+            purchases,
+        }
+    }
+
+    /// Number of trades needed to buy the planned quantities, splitting each
+    /// seller's purchases like the seller cards do.
+    pub fn trades(&self, limits: TradeLimits) -> usize {
+        let mut purchases_by_seller: HashMap<&str, Vec<Order>> = HashMap::new();
+        for purchase in &self.purchases {
+            let mut order = purchase.order.clone();
+            order.quantity = purchase.quantity;
+            purchases_by_seller
+                .entry(purchase.order.user.ingame_name.as_str())
+                .or_default()
+                .push(order);
+        }
+        purchases_by_seller
+            .values()
+            .map(|orders| split_into_trades(orders, limits).len())
+            .sum()
+    }
+}
+
+/// Records which knapsack capacities each chunk was taken at, one bit per
+/// chunk and capacity, so large budgets don't need a table of `bool`s.
+pub(crate) struct TakenChunks {
+    capacities: usize,
+    bits: Vec<u64>,
+}
+
+impl TakenChunks {
+    pub(crate) fn new(chunks: usize, capacities: usize) -> Self {
+        Self {
+            capacities,
+            bits: vec![0; (chunks * capacities).div_ceil(64)],
+        }
+    }
+
+    pub(crate) fn set(&mut self, chunk: usize, capacity: usize) {
+        let bit = chunk * self.capacities + capacity;
+        self.bits[bit / 64] |= 1 << (bit % 64);
+    }
+
+    pub(crate) fn get(&self, chunk: usize, capacity: usize) -> bool {
+        let bit = chunk * self.capacities + capacity;
+        self.bits[bit / 64] & (1 << (bit % 64)) != 0
+    }
+}
+
+/// Price per item we expect to pay for a processed order.
+pub(crate) fn unit_price(order: &Order) -> u32 {
+    order.price_to_offer.unwrap_or(order.platinum).max(1)
+}
+
+/// Picks which processed orders to buy, allowing partial quantities, without
+/// spending more than `budget` platinum.
+pub fn optimize_purchases(
+    orders: &[Order],
+    budget: u32,
+    goal: OptimizationGoal,
+) -> PurchasePlan {
+    let quantities = match goal {
+        OptimizationGoal::MaxDucats => max_ducats_quantities(orders, budget),
+        OptimizationGoal::MaxTrades => max_trades_quantities(orders, budget),
+    };
+    PurchasePlan::from_quantities(orders, &quantities)
+}
+
+/// Bounded knapsack over the budget. Each order is split into chunks of
+/// 1, 2, 4, ... items so every quantity up to the order's total can be chosen.
+fn max_ducats_quantities(orders: &[Order], budget: u32) -> Vec<u32> {
+    let total_cost: u64 = orders
+        .iter()
+        .map(|o| u64::from(unit_price(o)) * u64::from(o.quantity))
+        .sum();
+    let budget = u64::from(budget).min(total_cost) as usize;
+
+    // (order index, items in chunk, chunk cost, chunk ducats)
+    let mut chunks: Vec<(usize, u32, usize, u32)> = Vec::new();
+    for (index, order) in orders.iter().enumerate() {
+        let ducats = order.ducats.unwrap_or_default();
+        if ducats == 0 {
+            continue;
+        }
+        let price = unit_price(order) as usize;
+        let mut remaining = order.quantity;
+        let mut size = 1;
+        while remaining > 0 {
+            let items = size.min(remaining);
+            let cost = items as usize * price;
+            // Chunks over budget can never be taken
+            if cost <= budget {
+                chunks.push((index, items, cost, items * ducats));
+            }
+            remaining -= items;
+            size *= 2;
+        }
+    }
+
+    let mut best = vec![0u32; budget + 1];
+    let mut taken = TakenChunks::new(chunks.len(), budget + 1);
+    for (chunk_index, &(_, _, cost, ducats)) in chunks.iter().enumerate() {
+        for spent in (cost..=budget).rev() {
+            let candidate = best[spent - cost] + ducats;
+            if candidate > best[spent] {
+                best[spent] = candidate;
+                taken.set(chunk_index, spent);
+            }
+        }
+    }
+
+    let mut quantities = vec![0u32; orders.len()];
+    let mut spent = budget;
+    for (chunk_index, &(order_index, items, cost, _)) in chunks.iter().enumerate().rev() {
+        if taken.get(chunk_index, spent) {
+            quantities[order_index] += items;
+            spent -= cost;
+        }
+    }
+    quantities
+}
+
+/// Trades with as many sellers as the budget allows, then spends what's left
+/// on the most ducats per platinum. Each seller is first bought one item of
+/// their cheapest order, cheapest sellers first, with ties going to the item
+/// worth more ducats.
+fn max_trades_quantities(orders: &[Order], budget: u32) -> Vec<u32> {
+    let mut quantities = vec![0u32; orders.len()];
+    let mut remaining = budget;
+
+    // Cheapest item with a known ducat value of every seller
+    let mut cheapest_by_seller: HashMap<&str, usize> = HashMap::new();
+    for (index, order) in orders.iter().enumerate() {
+        if order.ducats.unwrap_or_default() == 0 || order.quantity == 0 {
+            continue;
+        }
+        let cheapest = cheapest_by_seller
+            .entry(order.user.ingame_name.as_str())
+            .or_insert(index);
+        if item_rank(order) < item_rank(&orders[*cheapest]) {
+            *cheapest = index;
+        }
+    }
+    let mut firsts: Vec<usize> = cheapest_by_seller.into_values().collect();
+    firsts.sort_by_key(|&index| (item_rank(&orders[index]), index));
+    for index in firsts {
+        let price = unit_price(&orders[index]);
+        if price <= remaining {
+            quantities[index] = 1;
+            remaining -= price;
+        }
+    }
+
+    // Top up with the best ducats per platinum. Sellers skipped above cost more
+    // than what's left, so no new trades are started.
+    let mut indices: Vec<usize> = (0..orders.len())
+        .filter(|&index| orders[index].ducats.unwrap_or_default() > 0)
+        .collect();
+    indices.sort_by(|&a, &b| {
+        let ratio =
+            |order: &Order| order.ducats.unwrap_or_default() as f32 / unit_price(order) as f32;
+        ratio(&orders[b]).total_cmp(&ratio(&orders[a])).then(a.cmp(&b))
+    });
+    for index in indices {
+        let price = unit_price(&orders[index]);
+        let quantity = (orders[index].quantity - quantities[index]).min(remaining / price);
+        quantities[index] += quantity;
+        remaining -= quantity * price;
+    }
+    quantities
+}
+
+/// Orders cheaper per item sort first, then those worth more ducats per item.
+fn item_rank(order: &Order) -> (u32, std::cmp::Reverse<u32>) {
+    (unit_price(order), std::cmp::Reverse(order.ducats.unwrap_or_default()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::external_lib::User;
+    use std::sync::Arc;
+
+    fn order(id: &str, seller: &str, price: u32, quantity: u32, ducats: u32) -> Order {
+        Order {
+            id: id.to_string(),
+            platinum: price,
+            quantity,
+            price_to_offer: Some(price),
+            ducats: Some(ducats),
+            user: Arc::new(User {
+                ingame_name: seller.to_string(),
+                ..Default::default()
+            }),
+            ..Default::default()
+        }
+    }
+
+    fn quantity_of(plan: &PurchasePlan, id: &str) -> u32 {
+        plan.purchases
+            .iter()
+            .find(|p| p.order.id == id)
+            .map_or(0, |p| p.quantity)
+    }
+
+    fn sellers(plan: &PurchasePlan) -> usize {
+        let mut sellers: Vec<&str> = plan
+            .purchases
+            .iter()
+            .map(|p| p.order.user.ingame_name.as_str())
+            .collect();
+        sellers.sort_unstable();
+        sellers.dedup();
+        sellers.len()
+    }
+
+    fn market() -> Vec<Order> {
+        vec![
+            order("a", "Alpha", 3, 10, 45),
+            order("b", "Bravo", 2, 4, 25),
+            order("c", "Charlie", 5, 3, 100),
+            order("d", "Delta", 4, 6, 65),
+            order("e", "Delta", 1, 2, 15),
+            order("f", "Echo", 7, 1, 100),
+        ]
+    }
+
+    #[test]
+    fn plans_stay_within_budget() {
+        let orders = market();
+        for goal in OptimizationGoal::ALL {
+            for budget in [0, 1, 7, 20, 33, 150, 1000] {
+                let plan = optimize_purchases(&orders, budget, goal);
+                assert!(
+                    plan.total_platinum <= budget,
+                    "{:?} spent {} of {}",
+                    goal,
+                    plan.total_platinum,
+                    budget
+                );
+                for purchase in &plan.purchases {
+                    assert!(purchase.quantity <= purchase.order.quantity);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn zero_budget_buys_nothing() {
+        for goal in OptimizationGoal::ALL {
+            assert!(optimize_purchases(&market(), 0, goal).purchases.is_empty());
+        }
+    }
+
+    #[test]
+    fn max_ducats_beats_cheapest_first() {
+        // Cheapest first would buy 3 of the 2 platinum items for 75 ducats
+        let orders = vec![
+            order("cheap", "Alpha", 2, 3, 25),
+            order("pricey", "Bravo", 6, 1, 100),
+        ];
+        let plan = optimize_purchases(&orders, 6, OptimizationGoal::MaxDucats);
+        assert_eq!(quantity_of(&plan, "pricey"), 1);
+        assert_eq!(plan.total_ducats, 100);
+    }
+
+    #[test]
+    fn max_ducats_buys_partial_orders() {
+        let orders = vec![order("a", "Alpha", 3, 10, 45)];
+        let plan = optimize_purchases(&orders, 10, OptimizationGoal::MaxDucats);
+        assert_eq!(quantity_of(&plan, "a"), 3);
+        assert_eq!(plan.total_platinum, 9);
+        assert_eq!(plan.total_ducats, 135);
+    }
+
+    #[test]
+    fn max_trades_buys_from_more_sellers_than_cheapest_order() {
+        // The cheapest order whole costs the whole budget and only trades once
+        let orders = vec![
+            order("whole", "Alpha", 1, 6, 15),
+            order("b", "Bravo", 2, 5, 45),
+            order("c", "Charlie", 2, 5, 45),
+            order("d", "Delta", 2, 5, 45),
+        ];
+        let plan = optimize_purchases(&orders, 6, OptimizationGoal::MaxTrades);
+        assert_eq!(sellers(&plan), 3);
+        assert_eq!(plan.trades(TradeLimits::default()), 3);
+        assert_eq!(plan.total_platinum, 6);
+    }
+
+    #[test]
+    fn max_trades_tops_up_with_best_ducats_per_platinum() {
+        let orders = vec![
+            order("a", "Alpha", 1, 5, 10),
+            order("b", "Bravo", 1, 5, 50),
+        ];
+        let plan = optimize_purchases(&orders, 4, OptimizationGoal::MaxTrades);
+        assert_eq!(sellers(&plan), 2);
+        assert_eq!(quantity_of(&plan, "a"), 1);
+        assert_eq!(quantity_of(&plan, "b"), 3);
+    }
+
+    #[test]
+    fn max_trades_skips_items_without_ducats() {
+        let orders = vec![order("a", "Alpha", 1, 5, 0), order("b", "Bravo", 3, 1, 45)];
+        let plan = optimize_purchases(&orders, 10, OptimizationGoal::MaxTrades);
+        assert_eq!(quantity_of(&plan, "a"), 0);
+        assert_eq!(quantity_of(&plan, "b"), 1);
+    }
+
+    #[test]
+    fn trades_are_counted_per_seller() {
+        let limits = TradeLimits {
+            max_stacks_per_trade: 6,
+            max_items_per_stack: 1,
+        };
+        let orders = vec![order("a", "Alpha", 1, 4, 15), order("b", "Alpha", 1, 4, 15)];
+        let plan = optimize_purchases(&orders, 100, OptimizationGoal::MaxDucats);
+        // 8 items from one seller need two trades of 6 slots
+        assert_eq!(plan.trades(limits), 2);
+    }
+}
@@ -0,0 +1,118 @@
+use crate::external_lib::Order;
+use crate::external_lib::optimizer::{PurchasePlan, TakenChunks, unit_price};
+use crate::external_lib::trades::TradeLimits;
+
+/// Purchases planned to reach a ducat target before Baro's visit.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct VisitPlan {
+    pub target_ducats: u32,
+    pub plan: PurchasePlan,
+    /// Number of trades needed across all sellers.
+    pub trades: usize,
+    pub estimated_minutes: u32,
+}
+
+impl VisitPlan {
+    pub fn is_target_reached(&self) -> bool {
+        self.plan.total_ducats >= self.target_ducats
+    }
+
+    /// Ducats of planned items already bought, given the items bought from
+    /// each order by ID.
+    pub fn bought_ducats(&self, bought_quantity: impl Fn(&str) -> u32) -> u32 {
+        self.plan
+            .purchases
+            .iter()
+            .map(|p| p.order.ducats.unwrap_or_default() * bought_quantity(&p.order.id).min(p.quantity))
+            .sum()
+    }
+
+    /// Progress toward the target from 0 to 1.
+    pub fn progress(&self, bought_quantity: impl Fn(&str) -> u32) -> f32 {
+        if self.target_ducats == 0 {
+            1.0
+        } else {
+            (self.bought_ducats(bought_quantity) as f32 / self.target_ducats as f32).min(1.0)
+        }
+    }
+}
+
+/// Works out the cheapest purchases (allowing partial quantities) that reach
+/// `target_ducats`. If the target can't be reached, every order with a known
+/// ducat value is bought.
+pub fn plan_visit(
+    orders: &[Order],
+    target_ducats: u32,
+    minutes_per_trade: u32,
+    limits: TradeLimits,
+) -> VisitPlan {
+    let quantities = cheapest_quantities(orders, target_ducats);
+    let plan = PurchasePlan::from_quantities(orders, &quantities);
+    let trades = plan.trades(limits);
+
+    VisitPlan {
+        target_ducats,
+        estimated_minutes: trades as u32 * minutes_per_trade,
+        trades,
+        plan,
+    }
+}
+
+/// Minimum-cost covering knapsack over ducats, capped at the target. Orders
+/// are split into chunks of 1, 2, 4, ... items like the budget optimizer.
+fn cheapest_quantities(orders: &[Order], target_ducats: u32) -> Vec<u32> {
+    let total_ducats: u32 = orders
+        .iter()
+        .map(|o| o.ducats.unwrap_or_default() * o.quantity)
+        .sum();
+    if total_ducats <= target_ducats {
+        return orders
+            .iter()
+            .map(|o| if o.ducats.unwrap_or_default() > 0 { o.quantity } else { 0 })
+            .collect();
+    }
+
+    let target = target_ducats as usize;
+
+    // (order index, items in chunk, chunk cost, chunk ducats)
+    let mut chunks: Vec<(usize, u32, u32, usize)> = Vec::new();
+    for (index, order) in orders.iter().enumerate() {
+        let ducats = order.ducats.unwrap_or_default();
+        if ducats == 0 {
+            continue;
+        }
+        let price = unit_price(order);
+        let mut remaining = order.quantity;
+        let mut size = 1;
+        while remaining > 0 {
+            let items = size.min(remaining);
+            chunks.push((index, items, items * price, (items * ducats) as usize));
+            remaining -= items;
+            size *= 2;
+        }
+    }
+
+    // cheapest[d] is the lowest cost to get at least d ducats
+    let mut cheapest = vec![u32::MAX; target + 1];
+    cheapest[0] = 0;
+    let mut taken = TakenChunks::new(chunks.len(), target + 1);
+    for (chunk_index, &(_, _, cost, ducats)) in chunks.iter().enumerate() {
+        for reached in (1..=target).rev() {
+            let before = cheapest[reached.saturating_sub(ducats)];
+            if before != u32::MAX && before + cost < cheapest[reached] {
+                cheapest[reached] = before + cost;
+                taken.set(chunk_index, reached);
+            }
+        }
+    }
+
+    let mut quantities = vec![0u32; orders.len()];
+    let mut reached = target;
+    for (chunk_index, &(order_index, items, _, ducats)) in chunks.iter().enumerate().rev() {
+        if reached > 0 && taken.get(chunk_index, reached) {
+            quantities[order_index] += items;
+            reached = reached.saturating_sub(ducats);
+        }
+    }
+    quantities
+}
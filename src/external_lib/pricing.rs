@@ -0,0 +1,112 @@
+use crate::external_lib::Order;
+
+/// Decides how much platinum per item to offer for an order.
+pub trait PricingStrategy: Send + Sync {
+    /// Price per item to offer, before clamping to the asking price.
+    fn offer(&self, order: &Order) -> u32;
+
+    /// Price per item to offer. Never above the asking price and never below 1.
+    fn price_to_offer(&self, order: &Order) -> u32 {
+        self.offer(order).max(1).min(order.platinum)
+    }
+}
+
+/// Offers the same price for every order.
+pub struct FixedPrice(pub u32);
+
+impl PricingStrategy for FixedPrice {
+    fn offer(&self, _order: &Order) -> u32 {
+        self.0
+    }
+}
+
+/// Highest percentage of the asking price worth offering.
+pub const MAX_OFFER_PERCENT: u32 = 100;
+
+/// Offers a percentage of the asking price, rounded down.
+pub struct PercentOfAsk(pub u32);
+
+impl PricingStrategy for PercentOfAsk {
+    fn offer(&self, order: &Order) -> u32 {
+        let offer = u64::from(order.platinum) * u64::from(self.0) / 100;
+        u32::try_from(offer).unwrap_or(u32::MAX)
+    }
+}
+
+/// Offers the price that reaches a target of ducats per platinum. Orders with
+/// an unknown ducat value are offered their asking price.
+pub struct DucatsPerPlatinum(pub u32);
+
+impl PricingStrategy for DucatsPerPlatinum {
+    fn offer(&self, order: &Order) -> u32 {
+        match order.ducats {
+            Some(ducats) if self.0 > 0 => ducats / self.0,
+            _ => order.platinum,
+        }
+    }
+}
+
+/// A price offered for orders with at least `min_quantity` items.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct QuantityTier {
+    pub min_quantity: u32,
+    pub price: u32,
+}
+
+/// Offers a price depending on the order's quantity, e.g. 3p each for fewer
+/// than 10 and 2p each for 10+. Quantities below every tier get the asking price.
+pub struct QuantityTiers(pub Vec<QuantityTier>);
+
+impl QuantityTiers {
+    /// Parses tiers written as `min_quantity:price` pairs, e.g. `1:3, 10:2`.
+    pub fn parse(tiers: &str) -> Result<Self, String> {
+        let mut tiers = tiers
+            .split(',')
+            .map(str::trim)
+            .filter(|tier| !tier.is_empty())
+            .map(|tier| {
+                let (min_quantity, price) = tier
+                    .split_once(':')
+                    .ok_or_else(|| format!("expected min_quantity:price, found '{}'", tier))?;
+                Ok(QuantityTier {
+                    min_quantity: min_quantity
+                        .trim()
+                        .parse()
+                        .map_err(|_| format!("invalid quantity in '{}'", tier))?,
+                    price: price
+                        .trim()
+                        .parse()
+                        .map_err(|_| format!("invalid price in '{}'", tier))?,
+                })
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+        tiers.sort_by_key(|tier| tier.min_quantity);
+        Ok(QuantityTiers(tiers))
+    }
+}
+
+impl PricingStrategy for QuantityTiers {
+    fn offer(&self, order: &Order) -> u32 {
+        self.0
+            .iter()
+            .rev()
+            .find(|tier| order.quantity >= tier.min_quantity)
+            .map_or(order.platinum, |tier| tier.price)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn percent_of_ask_does_not_overflow() {
+        let order = Order {
+            platinum: 1_000_000,
+            ..Default::default()
+        };
+        assert_eq!(PercentOfAsk(75).offer(&order), 750_000);
+        assert_eq!(PercentOfAsk(u32::MAX).offer(&order), u32::MAX);
+        assert_eq!(PercentOfAsk(u32::MAX).price_to_offer(&order), 1_000_000);
+    }
+}
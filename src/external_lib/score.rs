@@ -0,0 +1,137 @@
+use crate::external_lib::Order;
+use serde::{Deserialize, Serialize};
+
+/// One thing the deal score takes into account.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum ScoreFactor {
+    Quantity,
+    Price,
+    Ducats,
+    Reputation,
+    Age,
+    GroupSize,
+}
+
+impl ScoreFactor {
+    pub const ALL: [ScoreFactor; 6] = [
+        ScoreFactor::Quantity,
+        ScoreFactor::Price,
+        ScoreFactor::Ducats,
+        ScoreFactor::Reputation,
+        ScoreFactor::Age,
+        ScoreFactor::GroupSize,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            ScoreFactor::Quantity => "Quantity",
+            ScoreFactor::Price => "Low price",
+            ScoreFactor::Ducats => "Ducats per platinum",
+            ScoreFactor::Reputation => "Seller reputation",
+            ScoreFactor::Age => "Recently updated",
+            ScoreFactor::GroupSize => "Seller group size",
+        }
+    }
+
+    /// The factor's raw value for an order. Higher is always better.
+    fn raw_value(&self, order: &Order, group_size: usize) -> f64 {
+        match self {
+            ScoreFactor::Quantity => order.quantity.into(),
+            ScoreFactor::Price => -f64::from(order.price_to_offer.unwrap_or(order.platinum)),
+            ScoreFactor::Ducats => order.ducats_per_platinum.unwrap_or_default().into(),
+            ScoreFactor::Reputation => order.user.reputation as f64,
+            ScoreFactor::Age => order.last_update.timestamp() as f64,
+            ScoreFactor::GroupSize => group_size as f64,
+        }
+    }
+}
+
+/// How much each factor counts toward the deal score.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ScoreWeights {
+    pub quantity: f32,
+    pub price: f32,
+    pub ducats: f32,
+    pub reputation: f32,
+    pub age: f32,
+    pub group_size: f32,
+}
+
+impl Default for ScoreWeights {
+    fn default() -> Self {
+        Self {
+            quantity: 1.0,
+            price: 1.0,
+            ducats: 2.0,
+            reputation: 0.5,
+            age: 0.5,
+            group_size: 1.0,
+        }
+    }
+}
+
+impl ScoreWeights {
+    pub fn weight(&self, factor: ScoreFactor) -> f32 {
+        match factor {
+            ScoreFactor::Quantity => self.quantity,
+            ScoreFactor::Price => self.price,
+            ScoreFactor::Ducats => self.ducats,
+            ScoreFactor::Reputation => self.reputation,
+            ScoreFactor::Age => self.age,
+            ScoreFactor::GroupSize => self.group_size,
+        }
+    }
+
+    pub fn set_weight(&mut self, factor: ScoreFactor, weight: f32) {
+        let field = match factor {
+            ScoreFactor::Quantity => &mut self.quantity,
+            ScoreFactor::Price => &mut self.price,
+            ScoreFactor::Ducats => &mut self.ducats,
+            ScoreFactor::Reputation => &mut self.reputation,
+            ScoreFactor::Age => &mut self.age,
+            ScoreFactor::GroupSize => &mut self.group_size,
+        };
+        *field = weight;
+    }
+}
+
+/// An order's deal score and what each factor contributed to it.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct DealScore {
+    pub total: f32,
+    pub contributions: [(ScoreFactor, f32); ScoreFactor::ALL.len()],
+}
+
+/// Scores every order in the seller groups, given as lists of indices into
+/// `orders`. Each factor is scaled to 0..1 across all the grouped orders, so
+/// the best order for a factor gets its full weight.
+pub fn score_orders(orders: &mut [Order], groups: &[Vec<usize>], weights: &ScoreWeights) {
+    let ranges = ScoreFactor::ALL.map(|factor| {
+        groups
+            .iter()
+            .flat_map(|group| {
+                let orders = &*orders;
+                group.iter().map(move |&index| factor.raw_value(&orders[index], group.len()))
+            })
+            .fold((f64::INFINITY, f64::NEG_INFINITY), |(min, max), value| {
+                (min.min(value), max.max(value))
+            })
+    });
+
+    for group in groups {
+        for &index in group {
+            let order = &mut orders[index];
+            let mut contributions = ScoreFactor::ALL.map(|factor| (factor, 0.0));
+            for ((factor, contribution), &(min, max)) in contributions.iter_mut().zip(&ranges) {
+                let value = factor.raw_value(order, group.len());
+                let scaled = if max > min { (value - min) / (max - min) } else { 1.0 };
+                *contribution = weights.weight(*factor) * scaled as f32;
+            }
+            order.deal_score = Some(DealScore {
+                total: contributions.iter().map(|(_, contribution)| contribution).sum(),
+                contributions,
+            });
+        }
+    }
+}
@@ -0,0 +1,149 @@
+use crate::external_lib::Order;
+use serde::{Deserialize, Serialize};
+use chrono::{DateTime, Utc};
+use std::cmp::Ordering;
+
+/// How `process_orders` ranks sellers.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub enum SortMode {
+    #[default]
+    DealScore,
+    TotalQuantity,
+    TotalDucats,
+    DucatsPerPlatinum,
+    TotalPlatinum,
+    OrderAge,
+    Reputation,
+    GroupSize,
+}
+
+impl SortMode {
+    pub const ALL: [SortMode; 8] = [
+        SortMode::DealScore,
+        SortMode::TotalQuantity,
+        SortMode::TotalDucats,
+        SortMode::DucatsPerPlatinum,
+        SortMode::TotalPlatinum,
+        SortMode::OrderAge,
+        SortMode::Reputation,
+        SortMode::GroupSize,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            SortMode::DealScore => "Deal score",
+            SortMode::TotalQuantity => "Total quantity",
+            SortMode::TotalDucats => "Total ducats",
+            SortMode::DucatsPerPlatinum => "Ducats per platinum",
+            SortMode::TotalPlatinum => "Total platinum (lowest first)",
+            SortMode::OrderAge => "Order age (newest first)",
+            SortMode::Reputation => "Reputation",
+            SortMode::GroupSize => "Seller group size",
+        }
+    }
+}
+
+/// Totals the sort modes compare, computed once per seller or order.
+struct SortKey<'a> {
+    name: &'a str,
+    favourite: bool,
+    quantity: u32,
+    ducats: u32,
+    platinum: u32,
+    last_update: Option<DateTime<Utc>>,
+    reputation: i64,
+    size: usize,
+    best_score: f32,
+}
+
+impl<'a> SortKey<'a> {
+    fn new(orders: impl IntoIterator<Item = &'a Order>) -> Self {
+        let mut key = SortKey {
+            name: "",
+            favourite: false,
+            quantity: 0,
+            ducats: 0,
+            platinum: 0,
+            last_update: None,
+            reputation: 0,
+            size: 0,
+            best_score: f32::NEG_INFINITY,
+        };
+        for order in orders {
+            if key.size == 0 {
+                key.name = &order.user.ingame_name;
+                key.favourite = order.is_favourite.unwrap_or_default();
+                key.reputation = order.user.reputation;
+            }
+            key.quantity += order.quantity;
+            key.ducats += order.ducats.unwrap_or_default() * order.quantity;
+            // Platinum we expect to pay for the whole order
+            key.platinum += order.price_to_offer.unwrap_or(order.platinum) * order.quantity;
+            key.last_update = key.last_update.max(Some(order.last_update));
+            key.size += 1;
+            if let Some(score) = &order.deal_score {
+                key.best_score = key.best_score.max(score.total);
+            }
+        }
+        key
+    }
+
+    fn ducats_per_platinum(&self) -> f64 {
+        if self.platinum == 0 {
+            0.0
+        } else {
+            f64::from(self.ducats) / f64::from(self.platinum)
+        }
+    }
+}
+
+/// Compares two sellers' or orders' keys by the sort mode only, best first.
+fn compare_by_mode(a: &SortKey, b: &SortKey, mode: SortMode) -> Ordering {
+    match mode {
+        SortMode::DealScore => b.best_score.total_cmp(&a.best_score),
+        SortMode::TotalQuantity => b.quantity.cmp(&a.quantity),
+        SortMode::TotalDucats => b.ducats.cmp(&a.ducats),
+        SortMode::DucatsPerPlatinum => b.ducats_per_platinum().total_cmp(&a.ducats_per_platinum()),
+        SortMode::TotalPlatinum => a.platinum.cmp(&b.platinum),
+        SortMode::OrderAge => b.last_update.cmp(&a.last_update),
+        SortMode::Reputation => b.reputation.cmp(&a.reputation),
+        SortMode::GroupSize => b.size.cmp(&a.size),
+    }
+}
+
+/// Sorts each seller's order indices and then the sellers themselves, with
+/// favourite sellers first. `groups` holds one list of indices into `orders`
+/// per seller.
+///
+/// Ties are broken by total quantity, most recent update and finally seller
+/// name and order ID, so the result doesn't depend on `HashMap` iteration order.
+pub fn sort_seller_groups(orders: &[Order], groups: &mut [Vec<usize>], mode: SortMode) {
+    for group in groups.iter_mut() {
+        group.sort_by(|&a, &b| {
+            let (a, b) = (&orders[a], &orders[b]);
+            compare_by_mode(&SortKey::new([a]), &SortKey::new([b]), mode)
+                .then_with(|| b.quantity.cmp(&a.quantity))
+                .then_with(|| b.last_update.cmp(&a.last_update))
+                .then_with(|| a.id.cmp(&b.id))
+        });
+    }
+
+    let mut keyed: Vec<(SortKey, Vec<usize>)> = groups
+        .iter_mut()
+        .map(|group| {
+            let group = std::mem::take(group);
+            (SortKey::new(group.iter().map(|&index| &orders[index])), group)
+        })
+        .collect();
+    keyed.sort_by(|(a, _), (b, _)| {
+        b.favourite
+            .cmp(&a.favourite)
+            .then_with(|| compare_by_mode(a, b, mode))
+            .then_with(|| compare_by_mode(a, b, SortMode::TotalQuantity))
+            .then_with(|| compare_by_mode(a, b, SortMode::OrderAge))
+            .then_with(|| a.name.cmp(b.name))
+    });
+    for (group, (_, sorted)) in groups.iter_mut().zip(keyed) {
+        *group = sorted;
+    }
+}
@@ -0,0 +1,34 @@
+use crate::external_lib::ItemsInSet;
+
+/// Credits charged per item when the metadata has no `trading_tax`, by rarity.
+const RARITY_TRADING_TAX: [(&str, u32); 4] = [
+    ("common", 2_000),
+    ("uncommon", 4_000),
+    ("rare", 8_000),
+    ("legendary", 1_000_000),
+];
+
+/// Credits charged for trading one of this item. Uses the metadata's
+/// `trading_tax` and falls back to the item's rarity.
+pub fn trading_tax(item: &ItemsInSet) -> Option<u32> {
+    item.trading_tax.or_else(|| {
+        let rarity = item.rarity.as_deref()?;
+        RARITY_TRADING_TAX
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(rarity))
+            .map(|&(_, credits)| credits)
+    })
+}
+
+/// Formats credits with thousands separators, e.g. `12,000`.
+pub fn format_credits(credits: u64) -> String {
+    let digits = credits.to_string();
+    let mut formatted = String::with_capacity(digits.len() + digits.len() / 3);
+    for (i, digit) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            formatted.push(',');
+        }
+        formatted.push(digit);
+    }
+    formatted
+}
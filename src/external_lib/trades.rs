@@ -0,0 +1,80 @@
+use crate::external_lib::Order;
+use crate::external_lib::optimizer::unit_price;
+use std::sync::Arc;
+
+/// Warframe's limits on what fits into one trade.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TradeLimits {
+    /// Item slots in the trade window.
+    pub max_stacks_per_trade: u32,
+    /// Items of one kind that fit into a single slot.
+    pub max_items_per_stack: u32,
+}
+
+impl Default for TradeLimits {
+    fn default() -> Self {
+        Self {
+            max_stacks_per_trade: MAX_STACKS_PER_TRADE,
+            max_items_per_stack: MAX_ITEMS_PER_STACK,
+        }
+    }
+}
+
+pub const MAX_STACKS_PER_TRADE: u32 = 6;
+
+/// Identical parts stack in one slot with a quantity, so a whole order of one
+/// item usually takes a single slot.
+pub const MAX_ITEMS_PER_STACK: u32 = 999;
+
+/// Items of one order placed into one trade slot.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TradeStack {
+    pub order_id: String,
+    pub item_name: Arc<str>,
+    pub quantity: u32,
+    pub platinum: u32,
+}
+
+/// One trade with a seller.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Trade {
+    pub stacks: Vec<TradeStack>,
+    pub platinum: u32,
+}
+
+impl Trade {
+    pub fn quantity(&self) -> u32 {
+        self.stacks.iter().map(|s| s.quantity).sum()
+    }
+}
+
+/// Splits a seller's orders into trades, filling each trade's slots in order.
+pub fn split_into_trades(orders: &[Order], limits: TradeLimits) -> Vec<Trade> {
+    let max_stacks = limits.max_stacks_per_trade.max(1) as usize;
+    let max_items = limits.max_items_per_stack.max(1);
+
+    let mut trades = Vec::new();
+    let mut current = Trade::default();
+    for order in orders {
+        let price = unit_price(order);
+        let mut remaining = order.quantity;
+        while remaining > 0 {
+            if current.stacks.len() == max_stacks {
+                trades.push(std::mem::take(&mut current));
+            }
+            let quantity = remaining.min(max_items);
+            current.stacks.push(TradeStack {
+                order_id: order.id.clone(),
+                item_name: order.item_name.clone().unwrap_or_default(),
+                quantity,
+                platinum: quantity * price,
+            });
+            current.platinum += quantity * price;
+            remaining -= quantity;
+        }
+    }
+    if !current.stacks.is_empty() {
+        trades.push(current);
+    }
+    trades
+}
@@ -0,0 +1,41 @@
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+
+pub const DEFAULT_WORLDSTATE_URL: &str = "https://api.warframestat.us/pc/voidTrader";
+
+/// Baro Ki'Teer's visit as reported by the worldstate.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct VoidTrader {
+    /// When Baro arrives at the relay.
+    pub activation: DateTime<Utc>,
+    /// When Baro leaves the relay.
+    pub expiry: DateTime<Utc>,
+    pub location: String,
+}
+
+impl VoidTrader {
+    pub fn is_active(&self, now: DateTime<Utc>) -> bool {
+        self.activation <= now && now < self.expiry
+    }
+}
+
+/// Fetches the void trader from a worldstate endpoint.
+pub async fn fetch_void_trader(
+    url: &str,
+) -> Result<VoidTrader, Box<dyn std::error::Error + Send + Sync>> {
+    let response = reqwest::get(url).await?.error_for_status()?;
+    Ok(response.json::<VoidTrader>().await?)
+}
+
+/// Formats a countdown as e.g. `2d 3h 15m`.
+pub fn format_countdown(duration: Duration) -> String {
+    let minutes = duration.num_minutes().max(0);
+    let (days, hours, minutes) = (minutes / (24 * 60), minutes / 60 % 24, minutes % 60);
+    if days > 0 {
+        format!("{}d {}h {}m", days, hours, minutes)
+    } else if hours > 0 {
+        format!("{}h {}m", hours, minutes)
+    } else {
+        format!("{}m", minutes)
+    }
+}
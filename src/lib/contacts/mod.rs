@@ -0,0 +1,113 @@
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use crate::lib;
+
+/// Hours after which a whisper without an outcome stops hiding its order.
+pub const CONTACT_EXPIRY_HOURS: u32 = 6;
+
+/// Where a negotiation with a seller stands.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum ContactState {
+    #[default]
+    Whispered,
+    Replied,
+    Accepted,
+    Declined,
+    Traded,
+    NoResponse,
+    /// The seller delisted the order, probably selling to someone else.
+    Delisted,
+}
+
+impl ContactState {
+    pub const ALL: [ContactState; 7] = [
+        ContactState::Whispered,
+        ContactState::Replied,
+        ContactState::Accepted,
+        ContactState::Declined,
+        ContactState::Traded,
+        ContactState::NoResponse,
+        ContactState::Delisted,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            ContactState::Whispered => "Whispered",
+            ContactState::Replied => "Replied",
+            ContactState::Accepted => "Accepted",
+            ContactState::Declined => "Declined",
+            ContactState::Traded => "Traded",
+            ContactState::NoResponse => "No response",
+            ContactState::Delisted => "Delisted",
+        }
+    }
+
+    /// Whether the seller answered or the trade settled. Contacts without an
+    /// outcome can expire.
+    pub fn has_outcome(&self) -> bool {
+        !matches!(self, ContactState::Whispered | ContactState::NoResponse)
+    }
+
+    /// Whether the trade may still happen, so the user is waiting on the seller.
+    pub fn is_waiting(&self) -> bool {
+        matches!(
+            self,
+            ContactState::Whispered | ContactState::Replied | ContactState::Accepted
+        )
+    }
+}
+
+/// A whisper sent about one order and how the seller answered.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ContactRecord {
+    pub order_id: String,
+    pub user: String,
+    pub item_name: Option<String>,
+    pub contacted_at: DateTime<Utc>,
+    /// When the state last changed.
+    pub updated_at: DateTime<Utc>,
+    pub message: String,
+    /// Platinum per item offered in the message.
+    pub price_offered: Option<u32>,
+    pub state: ContactState,
+}
+
+impl ContactRecord {
+    pub fn new(order: &lib::Order, message: String, now: DateTime<Utc>) -> Self {
+        Self {
+            order_id: order.id.clone(),
+            user: order.user.ingame_name.clone(),
+            item_name: order.item_name.as_deref().map(str::to_string),
+            contacted_at: now,
+            updated_at: now,
+            message,
+            price_offered: order.price_to_offer,
+            state: ContactState::Whispered,
+        }
+    }
+
+    /// When the order stops being hidden, or `None` if it stays hidden. An
+    /// `expiry` of zero never expires contacts.
+    pub fn eligible_again_at(&self, expiry: Duration) -> Option<DateTime<Utc>> {
+        if self.state.has_outcome() || expiry.is_zero() {
+            None
+        } else {
+            Some(self.contacted_at + expiry)
+        }
+    }
+
+    pub fn is_expired(&self, expiry: Duration, now: DateTime<Utc>) -> bool {
+        self.eligible_again_at(expiry).is_some_and(|at| at <= now)
+    }
+
+    /// A record for an order ID saved before contact records existed.
+    pub fn migrated(order_id: String, now: DateTime<Utc>) -> Self {
+        Self {
+            order_id,
+            contacted_at: now,
+            updated_at: now,
+            ..Default::default()
+        }
+    }
+}
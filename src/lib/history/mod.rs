@@ -0,0 +1,138 @@
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use crate::lib;
+use crate::lib::storage::Storage;
+
+/// Days of fetch snapshots kept by default.
+pub const HISTORY_RETENTION_DAYS: u32 = 30;
+
+/// Sellers and items listed at one price.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct PriceLevel {
+    pub sellers: u32,
+    pub quantity: u32,
+}
+
+/// Visible sell orders of one item, grouped by asking price.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct ItemSnapshot {
+    pub price_levels: BTreeMap<u32, PriceLevel>,
+}
+
+/// Orders seen by one fetch.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub taken_at: DateTime<Utc>,
+    /// Keyed by item `url_name`.
+    pub items: BTreeMap<Arc<str>, ItemSnapshot>,
+}
+
+impl Snapshot {
+    pub fn from_orders(orders: &[lib::Order], taken_at: DateTime<Utc>) -> Self {
+        let mut items: BTreeMap<Arc<str>, ItemSnapshot> = BTreeMap::new();
+        for order in orders {
+            let Some(item_url) = &order.item_url else {
+                continue;
+            };
+            let item = items.entry(item_url.clone()).or_default();
+            if &*order.order_type != "sell" || !order.visible {
+                continue;
+            }
+            let level = item.price_levels.entry(order.platinum).or_default();
+            level.sellers += 1;
+            level.quantity += order.quantity;
+        }
+        Self { taken_at, items }
+    }
+}
+
+/// One point of an item's price series.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PricePoint {
+    pub taken_at: DateTime<Utc>,
+    /// Lowest asking price, or `None` if nobody was selling.
+    pub min_price: Option<u32>,
+    /// Sellers asking at most the cheap price.
+    pub cheap_sellers: u32,
+    /// Items listed at most at the cheap price.
+    pub cheap_quantity: u32,
+}
+
+/// Fetch snapshots kept across app restarts.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PriceHistory {
+    snapshots: Vec<Snapshot>,
+}
+
+impl PriceHistory {
+    const STORAGE_KEY: &'static str = "price_history";
+
+    pub fn load() -> Self {
+        let storage = Storage::new();
+        match storage.get(Self::STORAGE_KEY) {
+            Ok(Some(data)) => serde_json::from_str(&data).unwrap_or_default(),
+            _ => Self::default(),
+        }
+    }
+
+    pub fn save(&self) {
+        let storage = Storage::new();
+        if let Ok(data) = serde_json::to_string(self) {
+            let _ = storage.set(Self::STORAGE_KEY, &data);
+        }
+    }
+
+    pub fn snapshots(&self) -> &[Snapshot] {
+        &self.snapshots
+    }
+
+    /// Records a fetch and drops snapshots older than `retention`, then saves.
+    pub fn record(&mut self, orders: &[lib::Order], taken_at: DateTime<Utc>, retention: Duration) {
+        self.snapshots.push(Snapshot::from_orders(orders, taken_at));
+        self.prune(taken_at - retention);
+        self.save();
+    }
+
+    /// Drops snapshots taken before `cutoff`.
+    pub fn prune(&mut self, cutoff: DateTime<Utc>) {
+        self.snapshots.retain(|snapshot| snapshot.taken_at >= cutoff);
+    }
+
+    pub fn clear(&mut self) {
+        self.snapshots.clear();
+        self.save();
+    }
+
+    /// Items that appear in any snapshot.
+    pub fn item_urls(&self) -> Vec<&str> {
+        let mut item_urls: Vec<&str> = self
+            .snapshots
+            .iter()
+            .flat_map(|snapshot| snapshot.items.keys().map(|item_url| &**item_url))
+            .collect();
+        item_urls.sort_unstable();
+        item_urls.dedup();
+        item_urls
+    }
+
+    /// Price series of an item over time, oldest first. Sellers asking at most
+    /// `cheap_price` count as cheap. Snapshots that didn't fetch the item are skipped.
+    pub fn series(&self, item_url: &str, cheap_price: u32) -> Vec<PricePoint> {
+        self.snapshots
+            .iter()
+            .filter_map(|snapshot| {
+                let item = snapshot.items.get(item_url)?;
+                let cheap_levels = item.price_levels.range(..=cheap_price).map(|(_, level)| level);
+                Some(PricePoint {
+                    taken_at: snapshot.taken_at,
+                    min_price: item.price_levels.keys().next().copied(),
+                    cheap_sellers: cheap_levels.clone().map(|level| level.sellers).sum(),
+                    cheap_quantity: cheap_levels.map(|level| level.quantity).sum(),
+                })
+            })
+            .collect()
+    }
+}
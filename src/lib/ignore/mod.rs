@@ -0,0 +1,34 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use crate::lib::filter::NamePattern;
+
+/// Hides orders from sellers whose in-game name matches `pattern`.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct IgnoreRule {
+    /// In-game name, where `*` matches any run of characters and `?` any single one.
+    pub pattern: String,
+    pub case_sensitive: bool,
+    pub reason: String,
+    pub added_at: DateTime<Utc>,
+    /// The rule stops applying after this time. `None` never expires.
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+impl IgnoreRule {
+    pub fn new(pattern: String, now: DateTime<Utc>) -> Self {
+        Self {
+            pattern,
+            added_at: now,
+            ..Default::default()
+        }
+    }
+
+    pub fn is_active(&self, now: DateTime<Utc>) -> bool {
+        self.expires_at.is_none_or(|at| at > now)
+    }
+
+    pub fn name_pattern(&self) -> NamePattern {
+        NamePattern::new(&self.pattern, self.case_sensitive)
+    }
+}
@@ -0,0 +1,190 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use crate::lib;
+use crate::lib::storage::Storage;
+
+/// A completed purchase from a seller.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Purchase {
+    pub order_id: String,
+    pub item_url: String,
+    pub item_name: String,
+    pub user: String,
+    pub quantity: u32,
+    /// Platinum paid for all items.
+    pub platinum: u32,
+    /// Ducats one item converts to.
+    pub ducats: u32,
+    pub bought_at: DateTime<Utc>,
+}
+
+impl Purchase {
+    pub fn total_ducats(&self) -> u32 {
+        self.ducats * self.quantity
+    }
+}
+
+/// Items traded in at Baro Ki'Teer's kiosk.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Conversion {
+    pub item_url: String,
+    pub quantity: u32,
+    pub ducats: u32,
+    pub converted_at: DateTime<Utc>,
+}
+
+/// Parts bought and not yet converted.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct InventoryItem {
+    pub item_url: String,
+    pub item_name: String,
+    pub quantity: u32,
+    pub ducats: u32,
+}
+
+/// Purchases and kiosk conversions kept across app restarts.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Ledger {
+    purchases: Vec<Purchase>,
+    conversions: Vec<Conversion>,
+    /// Items bought so far by order ID, checked for every card.
+    #[serde(skip)]
+    bought_quantities: HashMap<String, u32>,
+}
+
+impl Ledger {
+    const STORAGE_KEY: &'static str = "purchase_ledger";
+
+    pub fn load() -> Self {
+        let storage = Storage::new();
+        let mut ledger: Self = match storage.get(Self::STORAGE_KEY) {
+            Ok(Some(data)) => serde_json::from_str(&data).unwrap_or_default(),
+            _ => Self::default(),
+        };
+        ledger.index_purchases();
+        ledger
+    }
+
+    /// Rebuilds `bought_quantities` after purchases were removed.
+    fn index_purchases(&mut self) {
+        self.bought_quantities.clear();
+        for purchase in &self.purchases {
+            *self.bought_quantities.entry(purchase.order_id.clone()).or_default() += purchase.quantity;
+        }
+    }
+
+    pub fn save(&self) {
+        let storage = Storage::new();
+        if let Ok(data) = serde_json::to_string(self) {
+            let _ = storage.set(Self::STORAGE_KEY, &data);
+        }
+    }
+
+    pub fn purchases(&self) -> &[Purchase] {
+        &self.purchases
+    }
+
+    pub fn conversions(&self) -> &[Conversion] {
+        &self.conversions
+    }
+
+    /// Items already bought from an order, over all of its purchases.
+    pub fn bought_quantity(&self, order_id: &str) -> u32 {
+        self.bought_quantities.get(order_id).copied().unwrap_or_default()
+    }
+
+    /// Records buying `quantity` items of a processed order for `platinum` in total.
+    pub fn record_purchase(
+        &mut self,
+        order: &lib::Order,
+        quantity: u32,
+        platinum: u32,
+        now: DateTime<Utc>,
+    ) {
+        *self.bought_quantities.entry(order.id.clone()).or_default() += quantity;
+        self.purchases.push(Purchase {
+            order_id: order.id.clone(),
+            item_url: order.item_url.as_deref().unwrap_or_default().to_string(),
+            item_name: order.item_name.as_deref().unwrap_or_default().to_string(),
+            user: order.user.ingame_name.clone(),
+            quantity,
+            platinum,
+            ducats: order.ducats.unwrap_or_default(),
+            bought_at: now,
+        });
+        self.save();
+    }
+
+    /// Corrects the platinum actually paid for a purchase.
+    pub fn set_purchase_platinum(&mut self, index: usize, platinum: u32) {
+        if let Some(purchase) = self.purchases.get_mut(index) {
+            purchase.platinum = platinum;
+            self.save();
+        }
+    }
+
+    pub fn remove_purchase(&mut self, index: usize) {
+        if index < self.purchases.len() {
+            self.purchases.remove(index);
+            self.index_purchases();
+            self.save();
+        }
+    }
+
+    /// Records trading items in at Baro's kiosk. Fails if fewer are on hand.
+    pub fn record_conversion(
+        &mut self,
+        item_url: &str,
+        quantity: u32,
+        now: DateTime<Utc>,
+    ) -> Result<(), String> {
+        let item = self
+            .inventory()
+            .into_iter()
+            .find(|item| item.item_url == item_url)
+            .ok_or_else(|| format!("No {} in inventory", item_url))?;
+        if quantity > item.quantity {
+            return Err(format!(
+                "Only {} {} in inventory",
+                item.quantity, item.item_name
+            ));
+        }
+        self.conversions.push(Conversion {
+            item_url: item_url.to_string(),
+            quantity,
+            ducats: item.ducats * quantity,
+            converted_at: now,
+        });
+        self.save();
+        Ok(())
+    }
+
+    /// Parts on hand, i.e. bought minus converted, by item.
+    pub fn inventory(&self) -> Vec<InventoryItem> {
+        let mut items: BTreeMap<&str, InventoryItem> = BTreeMap::new();
+        for purchase in &self.purchases {
+            let item = items
+                .entry(&purchase.item_url)
+                .or_insert_with(|| InventoryItem {
+                    item_url: purchase.item_url.clone(),
+                    item_name: purchase.item_name.clone(),
+                    ..Default::default()
+                });
+            item.quantity += purchase.quantity;
+            item.ducats = purchase.ducats;
+        }
+        for conversion in &self.conversions {
+            if let Some(item) = items.get_mut(conversion.item_url.as_str()) {
+                item.quantity = item.quantity.saturating_sub(conversion.quantity);
+            }
+        }
+        items
+            .into_values()
+            .filter(|item| item.quantity > 0)
+            .collect()
+    }
+}
@@ -1,15 +1,57 @@
+pub mod contacts;
+pub mod history;
+pub mod ignore;
+pub mod ledger;
+pub mod reports;
 pub mod settings;
 pub mod storage;
 
 pub use crate::external_lib::Order;
+pub use crate::external_lib::OrderFilter;
+pub use crate::external_lib::filter;
+pub use crate::external_lib::PricingStrategy;
+pub use crate::external_lib::pricing;
+pub use crate::external_lib::ScoreWeights;
+pub use crate::external_lib::score;
+pub use crate::external_lib::SortMode;
+pub use crate::external_lib::format_credits;
+pub use crate::external_lib::TradeLimits;
+pub use crate::external_lib::split_into_trades;
+pub use crate::external_lib::trades;
+pub use crate::external_lib::OptimizationGoal;
+pub use crate::external_lib::PurchasePlan;
+pub use crate::external_lib::optimize_purchases;
+pub use crate::external_lib::VisitPlan;
+pub use crate::external_lib::plan_visit;
+pub use crate::external_lib::EXPRESSION_HELP;
+pub use crate::external_lib::ParseError;
+pub use crate::external_lib::parse_filter_expression;
+pub use crate::external_lib::OrderLifecycle;
+pub use crate::external_lib::diff_orders;
 pub use crate::external_lib::User;
+pub use crate::external_lib::FetchedOrders;
+pub use crate::external_lib::ItemsInSet;
+pub use crate::external_lib::LANGUAGES;
+pub use crate::external_lib::format_age;
 pub use crate::external_lib::default_order_filter;
 pub use crate::external_lib::PROFITABLE_ITEM_NAMES;
 pub use crate::external_lib::fetch_all_orders;
 pub use crate::external_lib::process_orders;
 pub use crate::external_lib::generate_message;
+pub use crate::external_lib::generate_combined_message;
 pub use crate::external_lib::generate_messages;
 pub use crate::external_lib::PRICE_TO_OFFER;
+pub use crate::external_lib::PLATINUM_BUDGET;
+pub use crate::external_lib::DUCAT_TARGET;
+pub use crate::external_lib::MINUTES_PER_TRADE;
+pub use crate::external_lib::OFFER_PERCENT;
+pub use crate::external_lib::TARGET_DUCATS_PER_PLATINUM;
+pub use crate::external_lib::QUANTITY_TIERS;
 pub use crate::external_lib::MIN_QUANTITY_TO_SEARCH;
 pub use crate::external_lib::MAX_PRICE_TO_SEARCH;
-
+pub use crate::external_lib::MAX_ORDER_AGE_HOURS;
+pub use crate::external_lib::REGIONS;
+pub use crate::external_lib::VoidTrader;
+pub use crate::external_lib::DEFAULT_WORLDSTATE_URL;
+pub use crate::external_lib::fetch_void_trader;
+pub use crate::external_lib::format_countdown;
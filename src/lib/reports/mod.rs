@@ -0,0 +1,221 @@
+use chrono::{DateTime, Datelike, Duration, Local, NaiveDate, Utc};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
+use crate::lib::ledger::Purchase;
+
+/// Days between Baro Ki'Teer's visits.
+const BARO_VISIT_INTERVAL_DAYS: i64 = 14;
+
+/// Items and sellers listed in each report.
+const REPORT_TOP_COUNT: usize = 3;
+
+/// How purchases are grouped into reports.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum ReportPeriod {
+    #[default]
+    Day,
+    Week,
+    BaroVisit,
+}
+
+impl ReportPeriod {
+    pub const ALL: [ReportPeriod; 3] = [ReportPeriod::Day, ReportPeriod::Week, ReportPeriod::BaroVisit];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            ReportPeriod::Day => "Per day",
+            ReportPeriod::Week => "Per week",
+            ReportPeriod::BaroVisit => "Per Baro visit",
+        }
+    }
+}
+
+/// Ducats bought of one item in a report.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct ItemYield {
+    pub item_name: String,
+    pub ducats: u32,
+    pub ducats_per_platinum: f32,
+}
+
+/// Spending and ducats over one period.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Report {
+    /// Day, week start or Baro arrival date, in local time.
+    pub period: NaiveDate,
+    pub purchases: usize,
+    pub platinum: u32,
+    pub ducats: u32,
+    pub ducats_per_platinum: f32,
+    /// Items with the most ducats per platinum, best first.
+    pub best_items: Vec<ItemYield>,
+    /// Sellers bought from most often, with their purchase counts.
+    pub top_sellers: Vec<(String, usize)>,
+}
+
+/// Groups purchases into reports, newest period first. Baro visits are counted
+/// from `baro_arrival`, and purchases count toward the next visit after them.
+/// Returns nothing for [`ReportPeriod::BaroVisit`] without a known arrival.
+pub fn build_reports(
+    purchases: &[Purchase],
+    period: ReportPeriod,
+    baro_arrival: Option<DateTime<Utc>>,
+) -> Vec<Report> {
+    let mut periods: BTreeMap<NaiveDate, Vec<&Purchase>> = BTreeMap::new();
+    for purchase in purchases {
+        let bought_on = purchase.bought_at.with_timezone(&Local).date_naive();
+        let start = match period {
+            ReportPeriod::Day => bought_on,
+            ReportPeriod::Week => {
+                bought_on - Duration::days(bought_on.weekday().num_days_from_monday().into())
+            }
+            ReportPeriod::BaroVisit => {
+                let Some(arrival) = baro_arrival else {
+                    return Vec::new();
+                };
+                let interval = Duration::days(BARO_VISIT_INTERVAL_DAYS);
+                let visits_ahead = (arrival - purchase.bought_at).num_seconds()
+                    .div_euclid(interval.num_seconds());
+                (arrival - interval * visits_ahead as i32).with_timezone(&Local).date_naive()
+            }
+        };
+        periods.entry(start).or_default().push(purchase);
+    }
+
+    periods
+        .into_iter()
+        .rev()
+        .map(|(period, purchases)| build_report(period, &purchases))
+        .collect()
+}
+
+fn build_report(period: NaiveDate, purchases: &[&Purchase]) -> Report {
+    let platinum: u32 = purchases.iter().map(|p| p.platinum).sum();
+    let ducats: u32 = purchases.iter().map(|p| p.total_ducats()).sum();
+
+    let mut items: HashMap<&str, (u32, u32)> = HashMap::new();
+    let mut sellers: HashMap<&str, usize> = HashMap::new();
+    for purchase in purchases {
+        let item = items.entry(&purchase.item_name).or_default();
+        item.0 += purchase.total_ducats();
+        item.1 += purchase.platinum;
+        *sellers.entry(&purchase.user).or_default() += 1;
+    }
+
+    let mut best_items: Vec<ItemYield> = items
+        .into_iter()
+        .map(|(item_name, (ducats, platinum))| ItemYield {
+            item_name: item_name.to_string(),
+            ducats,
+            ducats_per_platinum: ratio(ducats, platinum),
+        })
+        .collect();
+    best_items.sort_by(|a, b| {
+        b.ducats_per_platinum
+            .total_cmp(&a.ducats_per_platinum)
+            .then_with(|| a.item_name.cmp(&b.item_name))
+    });
+    best_items.truncate(REPORT_TOP_COUNT);
+
+    let mut top_sellers: Vec<(String, usize)> = sellers
+        .into_iter()
+        .map(|(user, count)| (user.to_string(), count))
+        .collect();
+    top_sellers.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    top_sellers.truncate(REPORT_TOP_COUNT);
+
+    Report {
+        period,
+        purchases: purchases.len(),
+        platinum,
+        ducats,
+        ducats_per_platinum: ratio(ducats, platinum),
+        best_items,
+        top_sellers,
+    }
+}
+
+fn ratio(ducats: u32, platinum: u32) -> f32 {
+    if platinum == 0 {
+        0.0
+    } else {
+        ducats as f32 / platinum as f32
+    }
+}
+
+/// File formats reports can be exported to.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ExportFormat {
+    Csv,
+    Json,
+}
+
+impl ExportFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "csv",
+            ExportFormat::Json => "json",
+        }
+    }
+}
+
+/// One row per report. Best items and top sellers are `;`-separated.
+pub fn to_csv(reports: &[Report]) -> String {
+    let mut csv = String::from(
+        "period,purchases,platinum,ducats,ducats_per_platinum,best_items,top_sellers\n",
+    );
+    for report in reports {
+        let best_items: Vec<&str> = report.best_items.iter().map(|i| i.item_name.as_str()).collect();
+        let top_sellers: Vec<&str> = report.top_sellers.iter().map(|(user, _)| user.as_str()).collect();
+        csv.push_str(&format!(
+            "{},{},{},{},{:.2},{},{}\n",
+            report.period,
+            report.purchases,
+            report.platinum,
+            report.ducats,
+            report.ducats_per_platinum,
+            csv_field(&best_items.join(";")),
+            csv_field(&top_sellers.join(";")),
+        ));
+    }
+    csv
+}
+
+/// Quotes a CSV field if it contains a delimiter or quote.
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// Writes reports to the downloads folder (or the working directory) and
+/// returns the file's path.
+pub fn export_reports(
+    reports: &[Report],
+    period: ReportPeriod,
+    format: ExportFormat,
+) -> Result<PathBuf, String> {
+    let contents = match format {
+        ExportFormat::Csv => to_csv(reports),
+        ExportFormat::Json => serde_json::to_string_pretty(reports).map_err(|e| e.to_string())?,
+    };
+    let period = match period {
+        ReportPeriod::Day => "daily",
+        ReportPeriod::Week => "weekly",
+        ReportPeriod::BaroVisit => "baro",
+    };
+    let file_name = format!(
+        "ducat_report_{}_{}.{}",
+        period,
+        Local::now().format("%Y%m%d_%H%M%S"),
+        format.extension(),
+    );
+    let path = dirs::download_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join(file_name);
+    std::fs::write(&path, contents).map_err(|e| e.to_string())?;
+    Ok(path)
+}
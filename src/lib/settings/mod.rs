@@ -1,13 +1,78 @@
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
 use crate::lib;
+use crate::lib::filter::{
+    Contacted, FavouriteSellers, MaxAge, MinDucatsPerPlatinum, NamePattern, NoAnomalies, Regions,
+    UserBlocklist,
+};
+use crate::lib::OrderFilter;
+use crate::lib::PricingStrategy;
+use crate::lib::pricing::{DucatsPerPlatinum, FixedPrice, PercentOfAsk, QuantityTiers};
+use std::sync::Arc;
+use crate::lib::contacts::{ContactRecord, ContactState};
+use crate::lib::ignore::IgnoreRule;
 use crate::lib::storage::Storage;
 
+/// How `process_orders` decides the price to offer.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub enum PricingStrategyKind {
+    #[default]
+    FixedPrice,
+    PercentOfAsk,
+    DucatsPerPlatinum,
+    QuantityTiers,
+}
+
+impl PricingStrategyKind {
+    pub const ALL: [PricingStrategyKind; 4] = [
+        PricingStrategyKind::FixedPrice,
+        PricingStrategyKind::PercentOfAsk,
+        PricingStrategyKind::DucatsPerPlatinum,
+        PricingStrategyKind::QuantityTiers,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            PricingStrategyKind::FixedPrice => "Fixed price",
+            PricingStrategyKind::PercentOfAsk => "Percentage of ask",
+            PricingStrategyKind::DucatsPerPlatinum => "Ducats per platinum target",
+            PricingStrategyKind::QuantityTiers => "Quantity tiers",
+        }
+    }
+}
+
 #[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct Settings {
     max_price_to_search: String,
     min_quantity_to_search: String,
+    /// Max price for favourite sellers, who are worth a small premium.
+    favourite_max_price: String,
+    /// Min quantity for favourite sellers.
+    favourite_min_quantity: String,
     price_to_offer: String,
     item_names: String,
+    max_order_age_hours: String,
+    /// Comma-separated region codes to search in. Empty means any region.
+    regions: String,
+    /// Extra filter expression, e.g. `quantity >= 5 OR price <= 2`. Empty means none.
+    filter_expression: String,
+    pricing_strategy: PricingStrategyKind,
+    offer_percent: String,
+    target_ducats_per_platinum: String,
+    /// Quantity tiers as `min_quantity:price` pairs, e.g. `1:3, 10:2`.
+    quantity_tiers: String,
+    platinum_budget: String,
+    optimization_goal: lib::OptimizationGoal,
+    sort_mode: lib::SortMode,
+    /// Minimum ducats per platinum at the offered price. `0` disables the filter.
+    min_ducats_per_platinum: String,
+    ducat_target: String,
+    minutes_per_trade: String,
+    /// Hide orders flagged as possible bait.
+    hide_anomalies: bool,
+    score_weights: lib::ScoreWeights,
 }
 
 impl Settings {
@@ -20,6 +85,14 @@ impl Settings {
         &self.min_quantity_to_search
     }
 
+    pub fn favourite_max_price(&self) -> &str {
+        &self.favourite_max_price
+    }
+
+    pub fn favourite_min_quantity(&self) -> &str {
+        &self.favourite_min_quantity
+    }
+
     pub fn price_to_offer(&self) -> &str {
         &self.price_to_offer
     }
@@ -28,6 +101,87 @@ impl Settings {
         &self.item_names
     }
 
+    pub fn max_order_age_hours(&self) -> &str {
+        &self.max_order_age_hours
+    }
+
+    pub fn regions(&self) -> &str {
+        &self.regions
+    }
+
+    pub fn filter_expression(&self) -> &str {
+        &self.filter_expression
+    }
+
+    pub fn pricing_strategy_kind(&self) -> PricingStrategyKind {
+        self.pricing_strategy
+    }
+
+    pub fn offer_percent(&self) -> &str {
+        &self.offer_percent
+    }
+
+    pub fn target_ducats_per_platinum(&self) -> &str {
+        &self.target_ducats_per_platinum
+    }
+
+    pub fn quantity_tiers(&self) -> &str {
+        &self.quantity_tiers
+    }
+
+    pub fn platinum_budget(&self) -> &str {
+        &self.platinum_budget
+    }
+
+    pub fn optimization_goal(&self) -> lib::OptimizationGoal {
+        self.optimization_goal
+    }
+
+    pub fn sort_mode(&self) -> lib::SortMode {
+        self.sort_mode
+    }
+
+    pub fn min_ducats_per_platinum(&self) -> &str {
+        &self.min_ducats_per_platinum
+    }
+
+    pub fn ducat_target(&self) -> &str {
+        &self.ducat_target
+    }
+
+    pub fn minutes_per_trade(&self) -> &str {
+        &self.minutes_per_trade
+    }
+
+    pub fn hide_anomalies(&self) -> bool {
+        self.hide_anomalies
+    }
+
+    pub fn score_weights(&self) -> lib::ScoreWeights {
+        self.score_weights
+    }
+
+    /// Builds the selected pricing strategy. Fails if the quantity tiers don't parse.
+    pub fn pricing_strategy(&self) -> Result<Arc<dyn PricingStrategy>, String> {
+        let strategy: Arc<dyn PricingStrategy> = match self.pricing_strategy {
+            PricingStrategyKind::FixedPrice => {
+                Arc::new(FixedPrice(self.price_to_offer.parse().unwrap_or_default()))
+            }
+            PricingStrategyKind::PercentOfAsk => {
+                // Hand-edited settings may hold any percentage
+                let percent = self.offer_percent.parse().unwrap_or_default();
+                Arc::new(PercentOfAsk(u32::min(percent, lib::pricing::MAX_OFFER_PERCENT)))
+            }
+            PricingStrategyKind::DucatsPerPlatinum => Arc::new(DucatsPerPlatinum(
+                self.target_ducats_per_platinum.parse().unwrap_or_default(),
+            )),
+            PricingStrategyKind::QuantityTiers => {
+                Arc::new(QuantityTiers::parse(&self.quantity_tiers)?)
+            }
+        };
+        Ok(strategy)
+    }
+
     // Setters
     pub fn set_max_price_to_search(&mut self, value: String) {
         self.max_price_to_search = value;
@@ -37,6 +191,14 @@ impl Settings {
         self.min_quantity_to_search = value;
     }
 
+    pub fn set_favourite_max_price(&mut self, value: String) {
+        self.favourite_max_price = value;
+    }
+
+    pub fn set_favourite_min_quantity(&mut self, value: String) {
+        self.favourite_min_quantity = value;
+    }
+
     pub fn set_price_to_offer(&mut self, value: String) {
         self.price_to_offer = value;
     }
@@ -44,6 +206,66 @@ impl Settings {
     pub fn set_item_names(&mut self, value: String) {
         self.item_names = value;
     }
+
+    pub fn set_max_order_age_hours(&mut self, value: String) {
+        self.max_order_age_hours = value;
+    }
+
+    pub fn set_regions(&mut self, value: String) {
+        self.regions = value;
+    }
+
+    pub fn set_filter_expression(&mut self, value: String) {
+        self.filter_expression = value;
+    }
+
+    pub fn set_pricing_strategy_kind(&mut self, value: PricingStrategyKind) {
+        self.pricing_strategy = value;
+    }
+
+    pub fn set_offer_percent(&mut self, value: String) {
+        self.offer_percent = value;
+    }
+
+    pub fn set_target_ducats_per_platinum(&mut self, value: String) {
+        self.target_ducats_per_platinum = value;
+    }
+
+    pub fn set_quantity_tiers(&mut self, value: String) {
+        self.quantity_tiers = value;
+    }
+
+    pub fn set_platinum_budget(&mut self, value: String) {
+        self.platinum_budget = value;
+    }
+
+    pub fn set_optimization_goal(&mut self, value: lib::OptimizationGoal) {
+        self.optimization_goal = value;
+    }
+
+    pub fn set_sort_mode(&mut self, value: lib::SortMode) {
+        self.sort_mode = value;
+    }
+
+    pub fn set_min_ducats_per_platinum(&mut self, value: String) {
+        self.min_ducats_per_platinum = value;
+    }
+
+    pub fn set_ducat_target(&mut self, value: String) {
+        self.ducat_target = value;
+    }
+
+    pub fn set_minutes_per_trade(&mut self, value: String) {
+        self.minutes_per_trade = value;
+    }
+
+    pub fn set_hide_anomalies(&mut self, value: bool) {
+        self.hide_anomalies = value;
+    }
+
+    pub fn set_score_weights(&mut self, value: lib::ScoreWeights) {
+        self.score_weights = value;
+    }
 }
 
 impl Default for Settings {
@@ -51,17 +273,46 @@ impl Default for Settings {
         Self {
             max_price_to_search: lib::MAX_PRICE_TO_SEARCH.to_string(),
             min_quantity_to_search: lib::MIN_QUANTITY_TO_SEARCH.to_string(),
+            favourite_max_price: lib::MAX_PRICE_TO_SEARCH.to_string(),
+            favourite_min_quantity: lib::MIN_QUANTITY_TO_SEARCH.to_string(),
             price_to_offer: lib::PRICE_TO_OFFER.to_string(),
             item_names: lib::PROFITABLE_ITEM_NAMES.join("\n").to_string(),
+            max_order_age_hours: lib::MAX_ORDER_AGE_HOURS.to_string(),
+            regions: String::new(),
+            filter_expression: String::new(),
+            pricing_strategy: PricingStrategyKind::default(),
+            offer_percent: lib::OFFER_PERCENT.to_string(),
+            target_ducats_per_platinum: lib::TARGET_DUCATS_PER_PLATINUM.to_string(),
+            quantity_tiers: lib::QUANTITY_TIERS.to_string(),
+            platinum_budget: lib::PLATINUM_BUDGET.to_string(),
+            optimization_goal: lib::OptimizationGoal::default(),
+            sort_mode: lib::SortMode::default(),
+            min_ducats_per_platinum: "0".to_string(),
+            ducat_target: lib::DUCAT_TARGET.to_string(),
+            minutes_per_trade: lib::MINUTES_PER_TRADE.to_string(),
+            hide_anomalies: false,
+            score_weights: lib::ScoreWeights::default(),
         }
     }
 }
 
+/// Splits a comma-separated regions setting into region codes.
+pub fn parse_regions(regions: &str) -> Vec<String> {
+    regions
+        .split(',')
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+        .collect()
+}
+
 // TODO: save presets as hashmap instead of array
 #[derive(Clone, Serialize, Deserialize)]
 pub struct Preset {
     pub(crate) name: String,
     settings: Settings,
+    /// The preset stops being applied after this time, e.g. when Baro arrives.
+    #[serde(default)]
+    pub(crate) active_until: Option<DateTime<Utc>>,
 }
 
 #[derive(Clone, Serialize, Deserialize)]
@@ -70,11 +321,69 @@ pub struct SettingsManager {
     pub(crate) presets: Vec<Preset>,
     pub(crate) current_preset_name: Option<String>,
 
+    /// Exact nicknames ignored before ignore rules existed. Moved into
+    /// `ignore_rules` on load.
+    #[serde(default, skip_serializing)]
+    ignored_user_nicknames: Vec<String>,
+
+    #[serde(default)]
+    ignore_rules: Vec<IgnoreRule>,
+
+    /// Nicknames of sellers ranked first, where `*` and `?` are wildcards.
+    #[serde(default)]
+    favourite_sellers: Vec<String>,
+
+    /// Order IDs contacted before contact records existed. Moved into
+    /// `contacts` on load.
+    #[serde(default, skip_serializing)]
+    contacted_order_ids: Vec<String>,
+
+    #[serde(default)]
+    contacts: Vec<ContactRecord>,
+
+    /// Position of each order's record in `contacts`, looked up for every card.
+    #[serde(skip)]
+    contact_index: HashMap<String, usize>,
+
+    /// Hours before a whisper without an outcome stops hiding its order. `0` never expires.
+    #[serde(default = "default_contact_expiry_hours")]
+    contact_expiry_hours: u32,
+
     #[serde(default)]
-    pub ignored_user_nicknames: Vec<String>,
+    worldstate_enabled: bool,
+
+    #[serde(default = "default_worldstate_url")]
+    worldstate_url: String,
+
+    #[serde(default = "default_max_items_per_trade_slot")]
+    max_items_per_trade_slot: u32,
 
+    /// Trades allowed per day, which depends on mastery rank. `0` means unknown.
     #[serde(default)]
-    pub contacted_order_ids: Vec<String>,
+    daily_trade_limit: u32,
+
+    /// Credits available for trading tax. `0` means unknown.
+    #[serde(default)]
+    credit_balance: u64,
+
+    #[serde(default = "default_history_retention_days")]
+    history_retention_days: u32,
+}
+
+fn default_history_retention_days() -> u32 {
+    lib::history::HISTORY_RETENTION_DAYS
+}
+
+fn default_max_items_per_trade_slot() -> u32 {
+    lib::trades::MAX_ITEMS_PER_STACK
+}
+
+fn default_contact_expiry_hours() -> u32 {
+    lib::contacts::CONTACT_EXPIRY_HOURS
+}
+
+fn default_worldstate_url() -> String {
+    lib::DEFAULT_WORLDSTATE_URL.to_string()
 }
 
 impl SettingsManager {
@@ -82,10 +391,46 @@ impl SettingsManager {
 
     pub fn load() -> Self {
         let storage = Storage::new();
-        match storage.get(Self::STORAGE_KEY) {
+        let mut manager: Self = match storage.get(Self::STORAGE_KEY) {
             Ok(Some(data)) => serde_json::from_str(&data).unwrap_or_default(),
             _ => Self::default(),
+        };
+        manager.index_contacts();
+        let now = Utc::now();
+        let migrated_contacts = manager.migrate_contacted_order_ids(now);
+        let migrated_ignores = manager.migrate_ignored_user_nicknames(now);
+        if migrated_contacts || migrated_ignores {
+            manager.save();
+        }
+        manager
+    }
+
+    /// Turns the old list of contacted order IDs into contact records.
+    /// Returns true if anything was migrated.
+    fn migrate_contacted_order_ids(&mut self, now: DateTime<Utc>) -> bool {
+        if self.contacted_order_ids.is_empty() {
+            return false;
+        }
+        for order_id in std::mem::take(&mut self.contacted_order_ids) {
+            if self.contact(&order_id).is_none() {
+                self.push_contact(ContactRecord::migrated(order_id, now));
+            }
+        }
+        true
+    }
+
+    /// Turns the old list of ignored nicknames into ignore rules.
+    /// Returns true if anything was migrated.
+    fn migrate_ignored_user_nicknames(&mut self, now: DateTime<Utc>) -> bool {
+        if self.ignored_user_nicknames.is_empty() {
+            return false;
+        }
+        for nickname in std::mem::take(&mut self.ignored_user_nicknames) {
+            if !self.ignore_rules.iter().any(|rule| rule.pattern == nickname) {
+                self.ignore_rules.push(IgnoreRule::new(nickname, now));
+            }
         }
+        true
     }
 
     pub fn save(&self) {
@@ -99,6 +444,7 @@ impl SettingsManager {
         let preset = Preset {
             name: name.clone(),
             settings: self.current_settings.clone(),
+            active_until: None,
         };
 
         // Remove existing preset with the same name if it exists
@@ -165,46 +511,289 @@ impl SettingsManager {
         false
     }
 
-    pub fn ignored_user_nicknames(&self) -> &Vec<String> {
-        &self.ignored_user_nicknames
+    /// Sets the time after which the named preset is no longer applied.
+    pub fn set_preset_active_until(&mut self, name: &str, until: Option<DateTime<Utc>>) {
+        if let Some(preset) = self.presets.iter_mut().find(|p| p.name == name) {
+            preset.active_until = until;
+            self.save();
+        }
+    }
+
+    /// Unloads the current preset if its active period has ended. The current
+    /// settings are kept, so edits made since loading it aren't lost. Returns
+    /// the name of the expired preset.
+    pub fn expire_current_preset(&mut self, now: DateTime<Utc>) -> Option<String> {
+        let name = self.current_preset_name.clone()?;
+        let preset = self.presets.iter().find(|p| p.name == name)?;
+        if preset.active_until.is_some_and(|until| until <= now) {
+            self.current_preset_name = None;
+            self.save();
+            Some(name)
+        } else {
+            None
+        }
+    }
+
+    pub fn worldstate_enabled(&self) -> bool {
+        self.worldstate_enabled
+    }
+
+    pub fn set_worldstate_enabled(&mut self, enabled: bool) {
+        self.worldstate_enabled = enabled;
+        self.save();
+    }
+
+    pub fn worldstate_url(&self) -> &str {
+        &self.worldstate_url
+    }
+
+    pub fn set_worldstate_url(&mut self, url: String) {
+        self.worldstate_url = url;
+        self.save();
+    }
+
+    /// Builds the order filter from the current settings, ignored users and
+    /// contacted orders. Favourite sellers get their own price and quantity
+    /// limits. Fails if the filter expression doesn't parse.
+    pub fn order_filter(
+        &self,
+        now: DateTime<Utc>,
+    ) -> Result<Box<dyn OrderFilter>, lib::ParseError> {
+        let settings = &self.current_settings;
+        let max_price = settings.max_price_to_search.parse::<u32>().unwrap_or_default();
+        let min_quantity = settings.min_quantity_to_search.parse::<u32>().unwrap_or_default();
+        let max_order_age_hours = settings.max_order_age_hours.parse::<u32>().unwrap_or_default();
+        let favourite_max_price = settings.favourite_max_price.parse::<u32>().unwrap_or_default();
+        let favourite_min_quantity =
+            settings.favourite_min_quantity.parse::<u32>().unwrap_or_default();
+
+        let favourite_filter =
+            lib::default_order_filter(favourite_max_price, favourite_min_quantity).and(self.favourites());
+
+        let filter = lib::default_order_filter(max_price, min_quantity)
+            .or(favourite_filter)
+            .and(Regions(parse_regions(&settings.regions).into_iter().collect()))
+            .and(Contacted(
+                self.contacts
+                    .iter()
+                    .filter(|c| !c.is_expired(self.contact_expiry(), now))
+                    .map(|c| c.order_id.clone())
+                    .collect(),
+            ))
+            .and(UserBlocklist(
+                self.ignore_rules
+                    .iter()
+                    .filter(|rule| rule.is_active(now))
+                    .map(IgnoreRule::name_pattern)
+                    .collect(),
+            ))
+            .boxed();
+
+        let filter = if max_order_age_hours == 0 {
+            filter
+        } else {
+            let max_age = chrono::Duration::hours(max_order_age_hours.into());
+            filter.and(MaxAge { max_age, now }).boxed()
+        };
+
+        let min_ducats_per_platinum =
+            settings.min_ducats_per_platinum.parse::<f32>().unwrap_or_default();
+        let filter = if min_ducats_per_platinum > 0.0 {
+            filter.and(MinDucatsPerPlatinum(min_ducats_per_platinum)).boxed()
+        } else {
+            filter
+        };
+
+        let filter = if settings.hide_anomalies {
+            filter.and(NoAnomalies).boxed()
+        } else {
+            filter
+        };
+
+        if settings.filter_expression.trim().is_empty() {
+            Ok(filter)
+        } else {
+            let expression = lib::parse_filter_expression(&settings.filter_expression)?;
+            Ok(filter.and(expression).boxed())
+        }
+    }
+
+    pub fn trade_limits(&self) -> lib::TradeLimits {
+        lib::TradeLimits {
+            max_items_per_stack: self.max_items_per_trade_slot,
+            ..Default::default()
+        }
+    }
+
+    pub fn set_max_items_per_trade_slot(&mut self, value: u32) {
+        self.max_items_per_trade_slot = value;
+        self.save();
+    }
+
+    pub fn daily_trade_limit(&self) -> u32 {
+        self.daily_trade_limit
     }
 
-    pub fn set_ignored_user_nicknames(&mut self, nicknames: Vec<String>) {
-        self.ignored_user_nicknames = nicknames;
+    pub fn set_daily_trade_limit(&mut self, value: u32) {
+        self.daily_trade_limit = value;
         self.save();
     }
 
-    pub fn add_ignored_user_nickname(&mut self, nickname: String) {
-        if !self.ignored_user_nicknames.contains(&nickname) {
-            self.ignored_user_nicknames.push(nickname);
+    pub fn credit_balance(&self) -> u64 {
+        self.credit_balance
+    }
+
+    pub fn set_credit_balance(&mut self, value: u64) {
+        self.credit_balance = value;
+        self.save();
+    }
+
+    pub fn history_retention_days(&self) -> u32 {
+        self.history_retention_days
+    }
+
+    pub fn set_history_retention_days(&mut self, value: u32) {
+        self.history_retention_days = value;
+        self.save();
+    }
+
+    pub fn ignore_rules(&self) -> &[IgnoreRule] {
+        &self.ignore_rules
+    }
+
+    /// Adds a rule for `pattern` unless one already exists.
+    pub fn add_ignore_rule(&mut self, pattern: String, now: DateTime<Utc>) {
+        if !self.ignore_rules.iter().any(|rule| rule.pattern == pattern) {
+            self.ignore_rules.push(IgnoreRule::new(pattern, now));
+            self.save();
+        }
+    }
+
+    pub fn set_ignore_rule(&mut self, index: usize, rule: IgnoreRule) {
+        if let Some(existing) = self.ignore_rules.get_mut(index) {
+            *existing = rule;
+            self.save();
+        }
+    }
+
+    pub fn remove_ignore_rule(&mut self, index: usize) {
+        if index < self.ignore_rules.len() {
+            self.ignore_rules.remove(index);
+            self.save();
+        }
+    }
+
+    pub fn favourite_sellers(&self) -> &[String] {
+        &self.favourite_sellers
+    }
+
+    /// Filter keeping orders from favourite sellers, matched ignoring case.
+    pub fn favourites(&self) -> FavouriteSellers {
+        FavouriteSellers(
+            self.favourite_sellers
+                .iter()
+                .map(|nickname| NamePattern::new(nickname, false))
+                .collect(),
+        )
+    }
+
+    pub fn add_favourite_seller(&mut self, nickname: String) {
+        if !self.favourite_sellers.contains(&nickname) {
+            self.favourite_sellers.push(nickname);
             self.save();
         }
     }
 
-    pub fn remove_ignored_user_nickname(&mut self, nickname: &str) {
-        self.ignored_user_nicknames.retain(|n| n != nickname);
+    pub fn remove_favourite_seller(&mut self, nickname: &str) {
+        self.favourite_sellers.retain(|n| n != nickname);
         self.save();
     }
 
-    pub fn contacted_order_ids(&self) -> &Vec<String> {
-        &self.contacted_order_ids
+    pub fn contact_expiry(&self) -> chrono::Duration {
+        chrono::Duration::hours(self.contact_expiry_hours.into())
+    }
+
+    pub fn contact_expiry_hours(&self) -> u32 {
+        self.contact_expiry_hours
+    }
+
+    pub fn set_contact_expiry_hours(&mut self, value: u32) {
+        self.contact_expiry_hours = value;
+        self.save();
+    }
+
+    pub fn contacts(&self) -> &[ContactRecord] {
+        &self.contacts
+    }
+
+    pub fn contact(&self, order_id: &str) -> Option<&ContactRecord> {
+        self.contact_index.get(order_id).map(|&index| &self.contacts[index])
+    }
+
+    /// Rebuilds `contact_index` after records were removed.
+    fn index_contacts(&mut self) {
+        self.contact_index = self
+            .contacts
+            .iter()
+            .enumerate()
+            .map(|(index, contact)| (contact.order_id.clone(), index))
+            .collect();
     }
-    pub fn set_contacted_order_ids(&mut self, ids: Vec<String>) {
-        self.contacted_order_ids = ids;
+
+    fn push_contact(&mut self, contact: ContactRecord) {
+        self.contact_index.insert(contact.order_id.clone(), self.contacts.len());
+        self.contacts.push(contact);
+    }
+
+    /// Records a whisper about an order. Whispering again restarts the negotiation.
+    pub fn record_contact(&mut self, order: &lib::Order, message: String, now: DateTime<Utc>) {
+        if let Some(index) = self.contact_index.get(&order.id) {
+            self.contacts.remove(*index);
+            self.index_contacts();
+        }
+        self.push_contact(ContactRecord::new(order, message, now));
         self.save();
     }
-    pub fn add_contacted_order_id(&mut self, id: String) {
-        if !self.contacted_order_ids.contains(&id) {
-            self.contacted_order_ids.push(id);
+
+    pub fn set_contact_state(&mut self, order_id: &str, state: ContactState, now: DateTime<Utc>) {
+        if let Some(&index) = self.contact_index.get(order_id) {
+            let contact = &mut self.contacts[index];
+            contact.state = state;
+            contact.updated_at = now;
             self.save();
         }
     }
-    pub fn remove_contacted_order_id(&mut self, id: &str) {
-        self.contacted_order_ids.retain(|x| x != id);
+
+    /// Marks contacts still waiting on a seller as delisted when their order
+    /// is gone. Returns how many were marked.
+    pub fn mark_contacts_delisted(
+        &mut self,
+        gone_order_ids: &HashSet<String>,
+        now: DateTime<Utc>,
+    ) -> usize {
+        let mut marked = 0;
+        for contact in &mut self.contacts {
+            if contact.state.is_waiting() && gone_order_ids.contains(&contact.order_id) {
+                contact.state = ContactState::Delisted;
+                contact.updated_at = now;
+                marked += 1;
+            }
+        }
+        if marked > 0 {
+            self.save();
+        }
+        marked
+    }
+
+    pub fn remove_contact(&mut self, order_id: &str) {
+        self.contacts.retain(|c| c.order_id != order_id);
+        self.index_contacts();
         self.save();
     }
-    pub fn clear_contacted_order_ids(&mut self) {
-        self.contacted_order_ids.clear();
+
+    pub fn clear_contacts(&mut self) {
+        self.contacts.clear();
+        self.contact_index.clear();
         self.save();
     }
 }
@@ -216,7 +805,18 @@ impl Default for SettingsManager {
             presets: Vec::new(),
             current_preset_name: None,
             ignored_user_nicknames: Vec::new(),
+            ignore_rules: Vec::new(),
+            favourite_sellers: Vec::new(),
             contacted_order_ids: Vec::new(),
+            contacts: Vec::new(),
+            contact_index: HashMap::new(),
+            contact_expiry_hours: default_contact_expiry_hours(),
+            worldstate_enabled: false,
+            worldstate_url: default_worldstate_url(),
+            max_items_per_trade_slot: default_max_items_per_trade_slot(),
+            daily_trade_limit: 0,
+            credit_balance: 0,
+            history_retention_days: default_history_retention_days(),
         }
     }
 }
\ No newline at end of file
@@ -4,10 +4,9 @@
 mod external_lib;
 mod lib;
 
-use std::collections::HashSet;
 use eframe::egui;
 use eframe::egui::{
-  Align, Button, DragValue, Frame, Layout, Rounding, ScrollArea, Spinner,
+  Align, Button, DragValue, Frame, Layout, ScrollArea, Spinner,
   Stroke, TextEdit,
 };
 use egui_notify::Toasts;
@@ -18,6 +17,16 @@ use std::fs::{OpenOptions};
 use std::io::{Write, BufReader, BufRead};
 use std::path::Path;
 
+/// Largest budget the optimizer accepts. Its table grows with the budget.
+const MAX_PLATINUM_BUDGET: u32 = 10_000;
+
+/// Largest ducat target the planner accepts, well above what one Baro visit
+/// can spend. Its table grows with the target.
+const MAX_DUCAT_TARGET: u32 = 20_000;
+
+/// How long to wait before refetching the worldstate after a failure or once Baro has left.
+const WORLDSTATE_RETRY_SECONDS: f64 = 300.0;
+
 #[derive(Clone, Serialize, Deserialize)]
 pub struct Preset {
   name: String,
@@ -28,57 +37,168 @@ pub struct Preset {
 }
 
 struct MyApp {
-  rx_fetch: mpsc::Receiver<Result<Vec<lib::Order>, String>>,
-  tx_fetch: mpsc::Sender<Result<Vec<lib::Order>, String>>,
+  rx_fetch: mpsc::Receiver<Result<lib::FetchedOrders, String>>,
+  tx_fetch: mpsc::Sender<Result<lib::FetchedOrders, String>>,
   rx_process: mpsc::Receiver<Result<Vec<lib::Order>, String>>,
   tx_process: mpsc::Sender<Result<Vec<lib::Order>, String>>,
-  orders: Option<Vec<lib::Order>>,
+  rx_worldstate: mpsc::Receiver<Result<lib::VoidTrader, String>>,
+  tx_worldstate: mpsc::Sender<Result<lib::VoidTrader, String>>,
+  /// Shared with processing runs so they don't copy every order.
+  orders: Option<std::sync::Arc<[lib::Order]>>,
+  /// Item metadata keyed by `url_name`, merged across fetches.
+  items: std::collections::HashMap<String, lib::ItemsInSet>,
   processed_orders: Option<Vec<lib::Order>>,
+  /// Cards of the processed list, rebuilt when orders are processed or the trade limits change.
+  seller_cards: Vec<SellerCard>,
+  /// Trade limits `seller_cards` were built with. `None` when they need rebuilding.
+  seller_cards_limits: Option<lib::TradeLimits>,
+  /// Orders delisted since they were fetched, kept across fetches and processing runs.
+  gone_order_ids: std::collections::HashSet<String>,
+  /// Pricing strategy the processed orders were priced with.
+  processed_pricing: Option<std::sync::Arc<dyn lib::PricingStrategy>>,
   loading_fetch: bool,
   loading_process: bool,
+  loading_worldstate: bool,
+  void_trader: Option<lib::VoidTrader>,
+  last_worldstate_fetch: f64,
   settings_manager: lib::settings::SettingsManager,
   toasts: Toasts,
   show_settings: bool,
   show_credits: bool,
   show_all_orders: bool,
+  show_optimizer: bool,
+  purchase_plan: Option<lib::PurchasePlan>,
+  show_planner: bool,
+  visit_plan: Option<lib::VisitPlan>,
+  price_history: lib::history::PriceHistory,
+  show_history: bool,
+  show_contacts: bool,
+  ledger: lib::ledger::Ledger,
+  show_inventory: bool,
+  show_reports: bool,
+  report_period: lib::reports::ReportPeriod,
+  /// Quantity to convert at Baro's kiosk, by item `url_name`.
+  conversion_quantities: std::collections::HashMap<String, u32>,
+  history_item_url: Option<String>,
   new_preset_name: String,
+  new_ignore_pattern: String,
+  /// Ignore rule whose text is being edited, by index, until editing ends.
+  ignore_rule_edit: Option<(usize, lib::ignore::IgnoreRule)>,
+  new_favourite_seller: String,
   show_delete_presets_confirmation: bool,
+  detail_item_url: Option<String>,
+  detail_language: String,
 }
 
 impl Default for MyApp {
   fn default() -> Self {
     let (tx_fetch, rx_fetch) = mpsc::channel();
     let (tx_process, rx_process) = mpsc::channel();
+    let (tx_worldstate, rx_worldstate) = mpsc::channel();
     Self {
       rx_fetch,
       tx_fetch,
       rx_process,
       tx_process,
+      rx_worldstate,
+      tx_worldstate,
       orders: None,
+      items: std::collections::HashMap::new(),
       processed_orders: None,
+      seller_cards: Vec::new(),
+      seller_cards_limits: None,
+      gone_order_ids: std::collections::HashSet::new(),
+      processed_pricing: None,
       loading_fetch: false,
       loading_process: false,
+      loading_worldstate: false,
+      void_trader: None,
+      last_worldstate_fetch: f64::NEG_INFINITY,
       settings_manager: lib::settings::SettingsManager::load(),
       toasts: Toasts::new(),
       show_settings: false,
       show_credits: false,
       show_all_orders: false,
+      show_optimizer: false,
+      purchase_plan: None,
+      show_planner: false,
+      visit_plan: None,
+      price_history: lib::history::PriceHistory::load(),
+      show_history: false,
+      show_contacts: false,
+      ledger: lib::ledger::Ledger::load(),
+      show_inventory: false,
+      show_reports: false,
+      report_period: lib::reports::ReportPeriod::default(),
+      conversion_quantities: std::collections::HashMap::new(),
+      history_item_url: None,
       new_preset_name: String::new(),
+      new_ignore_pattern: String::new(),
+      ignore_rule_edit: None,
+      new_favourite_seller: String::new(),
       show_delete_presets_confirmation: false,
+      detail_item_url: None,
+      detail_language: String::from("en"),
     }
   }
 }
 
+impl MyApp {
+  /// Fetches Baro Ki'Teer's visit from the configured worldstate URL.
+  fn fetch_void_trader(&mut self) {
+    self.loading_worldstate = true;
+    let tx = self.tx_worldstate.clone();
+    let url = self.settings_manager.worldstate_url().to_string();
+
+    std::thread::spawn(move || {
+      let rt = tokio::runtime::Runtime::new().unwrap();
+      let result = rt
+          .block_on(lib::fetch_void_trader(&url))
+          .map_err(|e| format!("{:?}", e));
+      let _ = tx.send(result);
+    });
+  }
+}
+
 impl eframe::App for MyApp {
   fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
     // Poll the fetch channel for new messages
     match self.rx_fetch.try_recv() {
       Ok(result) => {
         match result {
-          Ok(data) => {
-            let message = format!("Successfully received fetched {:?} orders", data.len());
+          Ok(fetched) => {
+            let mut data = fetched.orders;
+            self.items.extend(fetched.items);
+            let mut message = format!("Successfully received fetched {:?} orders", data.len());
+            if let Some(previous) = &self.orders {
+              let diff = lib::diff_orders(previous, &data);
+              diff.apply(&mut data);
+              if let Some(processed_orders) = &mut self.processed_orders {
+                diff.apply(processed_orders);
+              }
+              message.push_str(&format!(
+                " ({} new, {} price dropped, {} gone)",
+                diff.count(lib::OrderLifecycle::New),
+                diff.count(lib::OrderLifecycle::PriceDropped),
+                diff.count(lib::OrderLifecycle::Gone),
+              ));
+              self.gone_order_ids.extend(diff.ids(lib::OrderLifecycle::Gone).map(str::to_string));
+            }
+            // Orders listed again aren't gone anymore
+            for order in &data {
+              self.gone_order_ids.remove(&order.id);
+            }
+            // Sellers who delisted probably sold to someone else, so stop waiting on them
+            let delisted = self
+                .settings_manager
+                .mark_contacts_delisted(&self.gone_order_ids, chrono::Utc::now());
+            if delisted > 0 {
+              self.toasts.info(format!("{} contacted sellers delisted their orders", delisted));
+            }
             info!("{}", message);
-            self.orders = Some(data);
+            let retention = chrono::Duration::days(self.settings_manager.history_retention_days().into());
+            self.price_history.record(&data, chrono::Utc::now(), retention);
+            self.orders = Some(data.into());
             self.toasts.success(message);
           }
           Err(err) => {
@@ -101,8 +221,15 @@ impl eframe::App for MyApp {
     match self.rx_process.try_recv() {
       Ok(result) => {
         match result {
-          Ok(data) => {
+          Ok(mut data) => {
+            // Processing may have started before the latest fetch
+            for order in &mut data {
+              if self.gone_order_ids.contains(&order.id) {
+                order.lifecycle = Some(lib::OrderLifecycle::Gone);
+              }
+            }
             self.processed_orders = Some(data);
+            self.seller_cards_limits = None;
             self.toasts.success("Successfully processed orders.");
           }
           Err(err) => {
@@ -119,10 +246,46 @@ impl eframe::App for MyApp {
       }
     }
 
+    // Poll the worldstate channel for Baro Ki'Teer's visit
+    match self.rx_worldstate.try_recv() {
+      Ok(result) => {
+        match result {
+          Ok(void_trader) => {
+            self.void_trader = Some(void_trader);
+          }
+          Err(err) => {
+            error!("Error fetching worldstate: {}", err);
+            self.toasts.error(format!("Error fetching worldstate: {}", err));
+          }
+        }
+        self.loading_worldstate = false;
+      }
+      Err(TryRecvError::Empty) => {}
+      Err(TryRecvError::Disconnected) => {
+        self.loading_worldstate = false;
+      }
+    }
+
+    let now = chrono::Utc::now();
+
+    let is_void_trader_stale = self.void_trader.as_ref().is_none_or(|t| t.expiry <= now);
+    if self.settings_manager.worldstate_enabled()
+        && !self.loading_worldstate
+        && is_void_trader_stale
+        && ctx.input(|i| i.time) - self.last_worldstate_fetch > WORLDSTATE_RETRY_SECONDS
+    {
+      self.last_worldstate_fetch = ctx.input(|i| i.time);
+      self.fetch_void_trader();
+    }
+
+    if let Some(preset_name) = self.settings_manager.expire_current_preset(now) {
+      self.toasts.info(format!(
+        "Preset \"{}\" expired. Its settings stay in use until you change them",
+        preset_name,
+      ));
+    }
+
     let settings = self.settings_manager.get_current_settings();
-    let max_price = settings.max_price_to_search().parse::<u32>().unwrap_or_default();
-    let min_quantity = settings.min_quantity_to_search().parse::<u32>().unwrap_or_default();
-    let offer_price = settings.price_to_offer().parse::<u32>().unwrap_or_default();
     let item_names: Vec<String> = settings
         .item_names()
         .lines()
@@ -141,6 +304,23 @@ impl eframe::App for MyApp {
           }
 
           ui.heading("Warframe Market Ducats Buyer");
+
+          if let Some(void_trader) = &self.void_trader {
+            if void_trader.is_active(now) {
+              ui.label(format!(
+                "Baro Ki'Teer is at {} for {}",
+                void_trader.location,
+                lib::format_countdown(void_trader.expiry - now),
+              ));
+            } else if void_trader.activation > now {
+              ui.label(format!(
+                "Baro Ki'Teer arrives at {} in {}",
+                void_trader.location,
+                lib::format_countdown(void_trader.activation - now),
+              ));
+            }
+          }
+
           ui.add_space(20.0);
 
           if ui.button("Credits").clicked() {
@@ -198,32 +378,43 @@ impl eframe::App for MyApp {
                 .add_sized([150.0, 30.0], Button::new("Filter & Process Orders"))
                 .clicked()
             {
-              self.loading_process = true;
-              let tx = self.tx_process.clone();
-              let orders = self.orders.clone();
-              let max_price = max_price;
-              let min_quantity = min_quantity;
-
-              let contacted_order_ids: std::collections::HashSet<_> =
-                  self.settings_manager.contacted_order_ids().iter().cloned().collect();
-              let ignored_nicknames = self.settings_manager.ignored_user_nicknames().iter().cloned().collect::<std::collections::HashSet<_>>();
-
-              std::thread::spawn(move || {
-                let filter_orders = |order: &lib::Order| -> bool {
-                  order.user.status == "ingame"
-                      && order.visible
-                      && order.order_type == "sell"
-                      && order.platinum <= max_price
-                      && order.quantity >= min_quantity
-                      && !contacted_order_ids.contains(&order.id)
-                      && !ignored_nicknames.contains(&order.user.ingame_name)
-                };
-
-                let processed_orders = orders
-                    .map(|o| lib::process_orders(o, filter_orders))
-                    .unwrap_or_else(Vec::new);
-                let _ = tx.send(Ok(processed_orders));
-              });
+              let filter_orders = self.settings_manager.order_filter(now);
+              let pricing = self.settings_manager.get_current_settings().pricing_strategy();
+
+              match (filter_orders, pricing) {
+                (Err(err), _) => {
+                  self.toasts.error(format!("Invalid filter expression at {}", err));
+                }
+                (_, Err(err)) => {
+                  self.toasts.error(format!("Invalid quantity tiers: {}", err));
+                }
+                (Ok(filter_orders), Ok(pricing)) => {
+                  self.loading_process = true;
+                  let tx = self.tx_process.clone();
+                  let orders = self.orders.clone();
+                  let sort_mode = self.settings_manager.get_current_settings().sort_mode();
+                  let score_weights = self.settings_manager.get_current_settings().score_weights();
+                  let favourites = self.settings_manager.favourites();
+                  // Messages keep the pricing used for processing until the next run
+                  self.processed_pricing = Some(pricing.clone());
+
+                  std::thread::spawn(move || {
+                    let processed_orders = orders
+                        .map(|o| {
+                          lib::process_orders(
+                            &o,
+                            filter_orders,
+                            pricing.as_ref(),
+                            sort_mode,
+                            &score_weights,
+                            &favourites,
+                          )
+                        })
+                        .unwrap_or_else(Vec::new);
+                    let _ = tx.send(Ok(processed_orders));
+                  });
+                }
+              }
             }
           });
         });
@@ -231,38 +422,145 @@ impl eframe::App for MyApp {
         let processed_orders_len = self.processed_orders.as_ref().map_or(0, |orders| orders.len());
         ui.label(format!("Processed orders length: {}", processed_orders_len));
 
+        ui.horizontal(|ui| {
+          if ui.button("Budget Optimizer").clicked() {
+            self.show_optimizer = !self.show_optimizer;
+          }
+          if ui.button("Baro Planner").clicked() {
+            self.show_planner = !self.show_planner;
+          }
+          if ui.button("Price History").clicked() {
+            self.show_history = !self.show_history;
+          }
+          if ui.button("Contacts").clicked() {
+            self.show_contacts = !self.show_contacts;
+          }
+          if ui.button("Inventory").clicked() {
+            self.show_inventory = !self.show_inventory;
+          }
+          if ui.button("Reports").clicked() {
+            self.show_reports = !self.show_reports;
+          }
+        });
+
         if self.loading_fetch {
           ui.add(Spinner::new().size(32.0));
         }
 
-        if let Some(processed_orders) = &self.processed_orders {
+        if let (Some(processed_orders), Some(pricing)) = (&self.processed_orders, &self.processed_pricing) {
           ui.label("Processed Orders:");
           ui.add_space(10.0);
 
-          ScrollArea::new(true).show(ui, |ui| {
-            for (i, order) in processed_orders.iter().enumerate() {
-              let frame_stroke = if order.is_with_group.unwrap_or(false) {
-                Stroke::new(2.0, ui.visuals().selection.stroke.color)
+          let trade_limits = self.settings_manager.trade_limits();
+          if self.seller_cards_limits != Some(trade_limits) {
+            self.seller_cards = seller_cards(processed_orders, pricing.as_ref(), trade_limits);
+            self.seller_cards_limits = Some(trade_limits);
+          }
+
+          // Only cards in view are laid out. The rest take up the height they
+          // had when last shown, or an estimate.
+          ScrollArea::new(true).show_viewport(ui, |ui, viewport| {
+            let content_top = ui.min_rect().top();
+            let mut skipped = 0.0;
+            for card in &mut self.seller_cards {
+              let top = ui.cursor().top() - content_top + skipped;
+              if top > viewport.max.y || top + card.height < viewport.min.y {
+                skipped += card.height;
+                continue;
+              }
+              ui.add_space(skipped);
+              skipped = 0.0;
+              let card_top = ui.cursor().top();
+
+              let seller_orders = &processed_orders[card.orders.clone()];
+              if let [order] = seller_orders {
+                let frame_stroke = Stroke::new(1.0, ui.visuals().extreme_bg_color);
+                let message = &card.messages[0];
+
+                Frame::new()
+                    .stroke(frame_stroke)
+                    .corner_radius(5)
+                    .show(ui, |ui| {
+                      let button = ui.add_sized([100.0, 100.0], Button::new(message.clone()));
+                      if button.clicked() {
+                        ui.ctx().copy_text(message.clone());
+                        self.settings_manager.record_contact(order, message.clone(), now);
+                      }
+                      ui.label(ducats_summary(order));
+                      contact_state_row(ui, &mut self.settings_manager, &order.id, now);
+                      purchase_row(
+                        ui,
+                        &mut self.ledger,
+                        &mut self.settings_manager,
+                        order,
+                        order.quantity,
+                        order.sum_to_offer.unwrap_or_default(),
+                        now,
+                      );
+                      if order_status_row(ui, order, now) {
+                        self.detail_item_url = order.item_url.as_deref().map(str::to_string);
+                      }
+                      if card.trades.len() > 1 {
+                        trades_list(ui, &order.user.ingame_name, &card.trades);
+                      }
+                    });
               } else {
-                Stroke::new(1.0, ui.visuals().extreme_bg_color)
-              };
-
-              // TODO: messages regenerate every time so new offer_price is applied but it should not apply until we click filter & process ordeers button
-              let message = lib::generate_message(order, offer_price);
-
-              Frame::none()
-                  .stroke(frame_stroke)
-                  .rounding(Rounding::same(5))
-                  .show(ui, |ui| {
-                    let button = ui.add_sized([100.0, 100.0], Button::new(message.clone()));
-                    if button.clicked() {
-                      ui.ctx().copy_text(message.clone());
-                      self.settings_manager.add_contacted_order_id(order.id.clone());
-                    }
-                  });
+                // One card per seller with several matching orders
+                let frame_stroke = Stroke::new(2.0, ui.visuals().selection.stroke.color);
+
+                Frame::new()
+                    .stroke(frame_stroke)
+                    .corner_radius(5)
+                    .show(ui, |ui| {
+                      let seller = &seller_orders[0];
+                      ui.label(egui::RichText::new(format!(
+                        "{} ({} items): {} ducats for {} platinum ({:.1} ducats/plat), {} credits tax",
+                        seller.user.ingame_name,
+                        seller_orders.len(),
+                        seller.seller_sum_ducats.unwrap_or_default(),
+                        seller.seller_sum_to_offer.unwrap_or_default(),
+                        seller.seller_ducats_per_platinum.unwrap_or_default(),
+                        lib::format_credits(seller.seller_sum_trading_tax.unwrap_or_default()),
+                      )).strong());
+                      for message in &card.messages {
+                        let button = ui.add_sized([100.0, 100.0], Button::new(message.clone()));
+                        if button.clicked() {
+                          ui.ctx().copy_text(message.clone());
+                          for order in seller_orders {
+                            self.settings_manager.record_contact(order, message.clone(), now);
+                          }
+                        }
+                      }
+                      for order in seller_orders {
+                        ui.label(format!(
+                          "{} x{} at {} platinum",
+                          order.item_name.as_deref().unwrap_or("Unknown"),
+                          order.quantity,
+                          order.platinum,
+                        ));
+                        ui.label(ducats_summary(order));
+                        contact_state_row(ui, &mut self.settings_manager, &order.id, now);
+                        purchase_row(
+                          ui,
+                          &mut self.ledger,
+                          &mut self.settings_manager,
+                          order,
+                          order.quantity,
+                          order.sum_to_offer.unwrap_or_default(),
+                          now,
+                        );
+                        if order_status_row(ui, order, now) {
+                          self.detail_item_url = order.item_url.as_deref().map(str::to_string);
+                        }
+                      }
+                      trades_list(ui, &seller.user.ingame_name, &card.trades);
+                    });
+              }
 
               ui.add_space(8.0);
+              card.height = ui.cursor().top() - card_top;
             }
+            ui.add_space(skipped);
           });
         }
       });
@@ -296,21 +594,25 @@ impl eframe::App for MyApp {
                     ui.group(|ui| {
                       ui.label(egui::RichText::new("Presets").strong());
 
-                      let preset_data: Vec<(String, bool)> = self.settings_manager
+                      let preset_data: Vec<(String, bool, Option<chrono::DateTime<chrono::Utc>>)> = self.settings_manager
                           .get_presets()
                           .iter()
                           .map(|preset| {
                             let is_current = self.settings_manager
                                 .get_current_preset_name()
                                 .map_or(false, |c| c == preset.name);
-                            (preset.name.clone(), is_current)
+                            (preset.name.clone(), is_current, preset.active_until)
                           })
                           .collect();
 
                       if preset_data.is_empty() {
                         ui.label("No presets saved.");
                       } else {
-                        for (preset_name, is_current) in &preset_data {
+                        let next_baro_arrival = self.void_trader
+                            .as_ref()
+                            .map(|t| t.activation)
+                            .filter(|activation| *activation > now);
+                        for (preset_name, is_current, active_until) in &preset_data {
                           egui::Frame::group(ui.style()).show(ui, |ui| {
                             ui.vertical(|ui| {
                               // Highlight current
@@ -336,6 +638,20 @@ impl eframe::App for MyApp {
                                   }
                                 }
                               });
+                              ui.horizontal(|ui| {
+                                let mut is_until_baro = active_until.is_some();
+                                let checkbox = ui.add_enabled(
+                                  is_until_baro || next_baro_arrival.is_some(),
+                                  egui::Checkbox::new(&mut is_until_baro, "Active until Baro arrives"),
+                                ).on_disabled_hover_text("Enable the worldstate to know when Baro arrives");
+                                if checkbox.changed() {
+                                  let until = if is_until_baro { next_baro_arrival } else { None };
+                                  self.settings_manager.set_preset_active_until(preset_name, until);
+                                }
+                                if let Some(until) = active_until {
+                                  ui.label(format!("({} left)", lib::format_countdown(*until - now)));
+                                }
+                              });
                             });
                           });
                           ui.add_space(4.0);
@@ -373,22 +689,195 @@ impl eframe::App for MyApp {
 
                     ui.label("Max Price:");
                     if let Ok(mut value) = settings.max_price_to_search().parse::<u32>() {
-                      if ui.add(DragValue::new(&mut value).clamp_range(0..=10).speed(0.02)).changed() {
+                      if ui.add(DragValue::new(&mut value).range(0..=10).speed(0.02)).changed() {
                         settings.set_max_price_to_search(value.to_string());
                       }
                     }
 
                     ui.label("Min Quantity:");
                     if let Ok(mut value) = settings.min_quantity_to_search().parse::<u32>() {
-                      if ui.add(DragValue::new(&mut value).clamp_range(0..=10).speed(0.02)).changed() {
+                      if ui.add(DragValue::new(&mut value).range(0..=10).speed(0.02)).changed() {
                         settings.set_min_quantity_to_search(value.to_string());
                       }
                     }
 
-                    ui.label("Offer Price:");
-                    if let Ok(mut value) = settings.price_to_offer().parse::<u32>() {
-                      if ui.add(DragValue::new(&mut value).clamp_range(0..=10).speed(0.02)).changed() {
-                        settings.set_price_to_offer(value.to_string());
+                    ui.label("Favourite Sellers Max Price:");
+                    if let Ok(mut value) = settings.favourite_max_price().parse::<u32>()
+                        && ui.add(DragValue::new(&mut value).range(0..=20).speed(0.02))
+                            .on_hover_text("Favourites are kept up to this price even above the max price")
+                            .changed()
+                    {
+                      settings.set_favourite_max_price(value.to_string());
+                    }
+
+                    ui.label("Favourite Sellers Min Quantity:");
+                    if let Ok(mut value) = settings.favourite_min_quantity().parse::<u32>()
+                        && ui.add(DragValue::new(&mut value).range(0..=10).speed(0.02))
+                            .on_hover_text("Favourites are kept down to this quantity even below the min quantity")
+                            .changed()
+                    {
+                      settings.set_favourite_min_quantity(value.to_string());
+                    }
+
+                    ui.label("Sort Sellers By:");
+                    let mut sort_mode = settings.sort_mode();
+                    egui::ComboBox::from_id_salt("sort_mode")
+                        .selected_text(sort_mode.label())
+                        .show_ui(ui, |ui| {
+                          for mode in lib::SortMode::ALL {
+                            ui.selectable_value(&mut sort_mode, mode, mode.label());
+                          }
+                        });
+                    if sort_mode != settings.sort_mode() {
+                      settings.set_sort_mode(sort_mode);
+                    }
+
+                    ui.collapsing("Deal Score Weights", |ui| {
+                      let mut score_weights = settings.score_weights();
+                      for factor in lib::score::ScoreFactor::ALL {
+                        ui.horizontal(|ui| {
+                          let mut weight = score_weights.weight(factor);
+                          if ui.add(DragValue::new(&mut weight).range(0.0..=10.0).speed(0.1)).changed() {
+                            score_weights.set_weight(factor, weight);
+                          }
+                          ui.label(factor.label());
+                        });
+                      }
+                      if score_weights != settings.score_weights() {
+                        settings.set_score_weights(score_weights);
+                      }
+                    });
+
+                    ui.label("Pricing Strategy:");
+                    let mut pricing_strategy_kind = settings.pricing_strategy_kind();
+                    egui::ComboBox::from_id_salt("pricing_strategy")
+                        .selected_text(pricing_strategy_kind.label())
+                        .show_ui(ui, |ui| {
+                          for kind in lib::settings::PricingStrategyKind::ALL {
+                            ui.selectable_value(&mut pricing_strategy_kind, kind, kind.label());
+                          }
+                        });
+                    if pricing_strategy_kind != settings.pricing_strategy_kind() {
+                      settings.set_pricing_strategy_kind(pricing_strategy_kind);
+                    }
+
+                    match pricing_strategy_kind {
+                      lib::settings::PricingStrategyKind::FixedPrice => {
+                        ui.label("Offer Price:");
+                        if let Ok(mut value) = settings.price_to_offer().parse::<u32>() {
+                          if ui.add(DragValue::new(&mut value).range(0..=10).speed(0.02)).changed() {
+                            settings.set_price_to_offer(value.to_string());
+                          }
+                        }
+                      }
+                      lib::settings::PricingStrategyKind::PercentOfAsk => {
+                        ui.label("Offer Percentage of Ask:");
+                        if let Ok(mut value) = settings.offer_percent().parse::<u32>()
+                            && ui.add(DragValue::new(&mut value).range(1..=lib::pricing::MAX_OFFER_PERCENT).suffix("%")).changed()
+                        {
+                          settings.set_offer_percent(value.to_string());
+                        }
+                      }
+                      lib::settings::PricingStrategyKind::DucatsPerPlatinum => {
+                        ui.label("Target Ducats per Platinum:");
+                        if let Ok(mut value) = settings.target_ducats_per_platinum().parse::<u32>()
+                            && ui.add(DragValue::new(&mut value).range(1..=100)).changed()
+                        {
+                          settings.set_target_ducats_per_platinum(value.to_string());
+                        }
+                      }
+                      lib::settings::PricingStrategyKind::QuantityTiers => {
+                        ui.label("Quantity Tiers (min_quantity:price, ...):");
+                        let mut quantity_tiers = settings.quantity_tiers().to_string();
+                        if ui.add(
+                          TextEdit::singleline(&mut quantity_tiers).hint_text(lib::QUANTITY_TIERS),
+                        ).on_hover_text("e.g. 1:3, 10:2 offers 3p each below 10 and 2p each for 10+").changed() {
+                          settings.set_quantity_tiers(quantity_tiers);
+                        }
+                        if let Err(err) = lib::pricing::QuantityTiers::parse(settings.quantity_tiers()) {
+                          ui.colored_label(egui::Color32::RED, err);
+                        }
+                      }
+                    }
+
+                    ui.label("Min Ducats per Platinum (0 = no limit):");
+                    if let Ok(mut value) = settings.min_ducats_per_platinum().parse::<f32>()
+                        && ui.add(DragValue::new(&mut value).range(0.0..=100.0).speed(0.1)).changed()
+                    {
+                      settings.set_min_ducats_per_platinum(value.to_string());
+                    }
+
+                    let mut hide_anomalies = settings.hide_anomalies();
+                    if ui.checkbox(&mut hide_anomalies, "Hide suspicious orders")
+                        .on_hover_text("Hide orders flagged as possible bait: huge quantities, price outliers and duplicate listings")
+                        .changed()
+                    {
+                      settings.set_hide_anomalies(hide_anomalies);
+                    }
+
+                    ui.label("Max Order Age (hours, 0 = no limit):");
+                    if let Ok(mut value) = settings.max_order_age_hours().parse::<u32>()
+                        && ui.add(DragValue::new(&mut value).range(0..=168)).changed()
+                    {
+                      settings.set_max_order_age_hours(value.to_string());
+                    }
+
+                    ui.label("Regions (none selected = any region):");
+                    let mut selected_regions = lib::settings::parse_regions(settings.regions());
+                    let mut regions_changed = false;
+                    ui.horizontal_wrapped(|ui| {
+                      for region in lib::REGIONS {
+                        let mut is_selected = selected_regions.iter().any(|r| r == region);
+                        if ui.checkbox(&mut is_selected, region).changed() {
+                          if is_selected {
+                            selected_regions.push(region.to_string());
+                          } else {
+                            selected_regions.retain(|r| r != region);
+                          }
+                          regions_changed = true;
+                        }
+                      }
+                    });
+                    if regions_changed {
+                      settings.set_regions(selected_regions.join(", "));
+                    }
+
+                    ui.add_space(10.0);
+
+                    ui.label("Filter Expression (optional):").on_hover_text(lib::EXPRESSION_HELP);
+                    let mut filter_expression = settings.filter_expression().to_string();
+                    if ui.add(
+                      TextEdit::multiline(&mut filter_expression)
+                          .code_editor()
+                          .hint_text("e.g. quantity >= 5 OR price <= 2")
+                          .desired_width(f32::INFINITY)
+                          .desired_rows(2),
+                    ).on_hover_text(lib::EXPRESSION_HELP).changed() {
+                      settings.set_filter_expression(filter_expression);
+                    }
+                    if !settings.filter_expression().trim().is_empty() {
+                      match lib::parse_filter_expression(settings.filter_expression()) {
+                        Ok(_) => {
+                          ui.colored_label(egui::Color32::LIGHT_GREEN, "Expression is valid");
+                        }
+                        Err(err) => {
+                          // Point at the error position under the offending line
+                          let expression = settings.filter_expression();
+                          let line_start = expression
+                              .chars()
+                              .take(err.position)
+                              .collect::<String>()
+                              .rfind('\n')
+                              .map_or(0, |i| i + 1);
+                          let line: String = expression[line_start..].lines().next().unwrap_or_default().to_string();
+                          let column = err.position - expression[..line_start].chars().count();
+                          ui.monospace(line);
+                          ui.colored_label(
+                            egui::Color32::RED,
+                            egui::RichText::new(format!("{}^", " ".repeat(column))).monospace(),
+                          );
+                          ui.colored_label(egui::Color32::RED, format!("Error at {}", err));
+                        }
                       }
                     }
 
@@ -411,50 +900,370 @@ impl eframe::App for MyApp {
                   // --- Global settings section ---
                   ui.group(|ui| {
                     ui.heading("Global Settings");
-                    ui.label("Ignored User Nicknames (one per line):");
-                    let mut ignored_nicknames_str = self.settings_manager.ignored_user_nicknames().join("\n");
-                    if ui.add(
-                      TextEdit::multiline(&mut ignored_nicknames_str)
-                          .hint_text("Enter user nicknames to ignore (one per line)")
-                          .desired_width(f32::INFINITY)
-                          .min_size([ui.available_width(), 60.0].into()),
-                    ).changed() {
-                      let new_list: Vec<String> = ignored_nicknames_str
-                          .lines()
-                          .map(|s| s.trim().to_string())
-                          .filter(|s| !s.is_empty())
-                          .collect();
-                      self.settings_manager.set_ignored_user_nicknames(new_list);
+                    ui.label("Ignored Sellers:");
+                    ui.horizontal(|ui| {
+                      ui.add(
+                        TextEdit::singleline(&mut self.new_ignore_pattern)
+                            .hint_text("Nickname, e.g. Seller123 or Bait*"),
+                      ).on_hover_text("* matches any characters and ? any single character");
+                      if ui.button("Ignore").clicked() && !self.new_ignore_pattern.trim().is_empty() {
+                        self.settings_manager.add_ignore_rule(self.new_ignore_pattern.trim().to_string(), now);
+                        self.new_ignore_pattern.clear();
+                      }
+                    });
+                    let rules = self.settings_manager.ignore_rules().to_vec();
+                    if !rules.is_empty() {
+                      let mut removed = None;
+                      egui::Grid::new("ignore_rules").striped(true).show(ui, |ui| {
+                        ui.strong("Pattern");
+                        ui.strong("Match case");
+                        ui.strong("Reason");
+                        ui.strong("Added");
+                        ui.strong("Expires");
+                        ui.strong("");
+                        ui.end_row();
+
+                        for (index, rule) in rules.iter().enumerate() {
+                          let mut edited = match &self.ignore_rule_edit {
+                            Some((editing, buffered)) if *editing == index => buffered.clone(),
+                            _ => rule.clone(),
+                          };
+                          let pattern = ui.add(TextEdit::singleline(&mut edited.pattern).desired_width(140.0));
+                          let mut is_committed = ui.checkbox(&mut edited.case_sensitive, "").changed();
+                          let reason = ui.add(
+                            TextEdit::singleline(&mut edited.reason).hint_text("Optional").desired_width(180.0),
+                          );
+                          ui.label(format!("{} ago", lib::format_age(now - rule.added_at)));
+                          egui::ComboBox::from_id_salt(("ignore_expiry", index))
+                              .selected_text(match rule.expires_at {
+                                None => "Never".to_string(),
+                                Some(at) if at <= now => "Expired".to_string(),
+                                Some(at) => format!("In {}", lib::format_countdown(at - now)),
+                              })
+                              .show_ui(ui, |ui| {
+                                if ui.selectable_label(rule.expires_at.is_none(), "Never").clicked() {
+                                  edited.expires_at = None;
+                                  is_committed = true;
+                                }
+                                for days in [1, 7, 30] {
+                                  if ui.selectable_label(false, format!("In {} days", days)).clicked() {
+                                    edited.expires_at = Some(now + chrono::Duration::days(days));
+                                    is_committed = true;
+                                  }
+                                }
+                              });
+                          if ui.small_button("Remove").clicked() {
+                            removed = Some(index);
+                          }
+                          ui.end_row();
+                          // Text is saved once editing ends, not on every keystroke
+                          if is_committed || pattern.lost_focus() || reason.lost_focus() {
+                            self.ignore_rule_edit = None;
+                            if edited != *rule {
+                              self.settings_manager.set_ignore_rule(index, edited);
+                            }
+                          } else if pattern.changed() || reason.changed() {
+                            self.ignore_rule_edit = Some((index, edited));
+                          }
+                        }
+                      });
+                      if let Some(index) = removed {
+                        self.ignore_rule_edit = None;
+                        self.settings_manager.remove_ignore_rule(index);
+                      }
                     }
 
                     ui.add_space(10.0);
-                    ui.label("Contacted Order IDs (one per line):");
-                    let mut contacted_ids = self.settings_manager.contacted_order_ids().join("\n");
-                    if ui.add(
-                      TextEdit::multiline(&mut contacted_ids)
-                          .hint_text("Order IDs you have already contacted (one per line)")
-                          .desired_width(f32::INFINITY)
-                          .min_size([ui.available_width(), 60.0].into()),
-                    ).changed() {
-                      let new_list: Vec<String> = contacted_ids
-                          .lines()
-                          .map(|s| s.trim().to_string())
-                          .filter(|s| !s.is_empty())
-                          .collect();
-                      self.settings_manager.set_contacted_order_ids(new_list);
+                    ui.label("Favourite Sellers:");
+                    ui.horizontal(|ui| {
+                      ui.add(
+                        TextEdit::singleline(&mut self.new_favourite_seller)
+                            .hint_text("Nickname, e.g. Seller123 or Trusty*"),
+                      ).on_hover_text("* matches any characters and ? any single character");
+                      if ui.button("Add").clicked() && !self.new_favourite_seller.trim().is_empty() {
+                        self.settings_manager.add_favourite_seller(self.new_favourite_seller.trim().to_string());
+                        self.new_favourite_seller.clear();
+                      }
+                    });
+                    let mut removed = None;
+                    ui.horizontal_wrapped(|ui| {
+                      for nickname in self.settings_manager.favourite_sellers() {
+                        if ui.small_button(format!("★ {} ✖", nickname)).on_hover_text("Remove from favourites").clicked() {
+                          removed = Some(nickname.clone());
+                        }
+                      }
+                    });
+                    if let Some(nickname) = removed {
+                      self.settings_manager.remove_favourite_seller(&nickname);
                     }
-                    if ui.button("Clear All Contacted Order IDs")
-                        .on_hover_text("Remove all contacted order IDs")
-                        .clicked() {
-                      self.settings_manager.clear_contacted_order_ids();
-                      self.toasts.success("All contacted order IDs cleared");
+
+                    ui.add_space(10.0);
+                    ui.horizontal(|ui| {
+                      ui.label("Items per trade slot:");
+                      let mut value = self.settings_manager.trade_limits().max_items_per_stack;
+                      if ui.add(DragValue::new(&mut value).range(1..=999))
+                          .on_hover_text("How many items of one kind fit into a single slot of the trade window")
+                          .changed()
+                      {
+                        self.settings_manager.set_max_items_per_trade_slot(value);
+                      }
+                    });
+                    ui.horizontal(|ui| {
+                      ui.label("Keep price history for:");
+                      let mut value = self.settings_manager.history_retention_days();
+                      if ui.add(DragValue::new(&mut value).range(1..=365).suffix(" days")).changed() {
+                        self.settings_manager.set_history_retention_days(value);
+                      }
+                      if ui.button("Clear History").clicked() {
+                        self.price_history.clear();
+                        self.history_item_url = None;
+                        self.toasts.success("Price history cleared");
+                      }
+                    });
+                    ui.horizontal(|ui| {
+                      ui.label("Credit balance:");
+                      let mut value = self.settings_manager.credit_balance();
+                      if ui.add(DragValue::new(&mut value).range(0..=u64::MAX).speed(1000))
+                          .on_hover_text("Credits available for trading tax. 0 disables the warning")
+                          .changed()
+                      {
+                        self.settings_manager.set_credit_balance(value);
+                      }
+                    });
+                    ui.horizontal(|ui| {
+                      ui.label("Daily trade limit:");
+                      let mut value = self.settings_manager.daily_trade_limit();
+                      if ui.add(DragValue::new(&mut value).range(0..=100))
+                          .on_hover_text("Trades you can make per day. 0 disables the warning")
+                          .changed()
+                      {
+                        self.settings_manager.set_daily_trade_limit(value);
+                      }
+                    });
+
+                    ui.add_space(10.0);
+                    let mut worldstate_enabled = self.settings_manager.worldstate_enabled();
+                    if ui.checkbox(&mut worldstate_enabled, "Show Baro Ki'Teer countdown")
+                        .on_hover_text("Fetch Baro's arrival time from a worldstate endpoint")
+                        .changed()
+                    {
+                      self.settings_manager.set_worldstate_enabled(worldstate_enabled);
+                      if !worldstate_enabled {
+                        self.void_trader = None;
+                      }
                     }
+                    ui.label("Worldstate URL:");
+                    ui.horizontal(|ui| {
+                      let mut worldstate_url = self.settings_manager.worldstate_url().to_string();
+                      if ui.add(
+                        TextEdit::singleline(&mut worldstate_url).hint_text(lib::DEFAULT_WORLDSTATE_URL),
+                      ).changed() {
+                        self.settings_manager.set_worldstate_url(worldstate_url);
+                      }
+                      if ui.button("Refresh").clicked() {
+                        // Forces a refetch on the next frame
+                        self.void_trader = None;
+                        self.last_worldstate_fetch = f64::NEG_INFINITY;
+                      }
+                    });
                   });
 
                 });
           });
     }
 
+    if self.show_optimizer {
+      egui::Window::new("Budget Optimizer")
+          .open(&mut self.show_optimizer)
+          .resizable(true)
+          .show(ctx, |ui| {
+            let settings = self.settings_manager.get_current_settings_mut();
+
+            ui.horizontal(|ui| {
+              ui.label("Budget:");
+              if let Ok(mut value) = settings.platinum_budget().parse::<u32>()
+                  && ui.add(DragValue::new(&mut value).range(0..=MAX_PLATINUM_BUDGET).suffix(" platinum")).changed()
+              {
+                settings.set_platinum_budget(value.to_string());
+              }
+            });
+
+            let mut goal = settings.optimization_goal();
+            egui::ComboBox::from_label("Goal")
+                .selected_text(goal.label())
+                .show_ui(ui, |ui| {
+                  for option in lib::OptimizationGoal::ALL {
+                    ui.selectable_value(&mut goal, option, option.label());
+                  }
+                });
+            if goal != settings.optimization_goal() {
+              settings.set_optimization_goal(goal);
+            }
+
+            let budget = settings.platinum_budget().parse::<u32>().unwrap_or_default().min(MAX_PLATINUM_BUDGET);
+            ui.add_enabled_ui(self.processed_orders.is_some(), |ui| {
+              if ui.button("Optimize").on_disabled_hover_text("Process orders first").clicked()
+                  && let Some(processed_orders) = &self.processed_orders
+              {
+                self.purchase_plan = Some(lib::optimize_purchases(processed_orders, budget, goal));
+              }
+            });
+
+            ui.add_space(10.0);
+
+            let Some(plan) = &self.purchase_plan else {
+              return;
+            };
+
+            ui.label(egui::RichText::new(format!(
+              "Total: {} platinum and {} credits for {} ducats across {} trades",
+              plan.total_platinum,
+              lib::format_credits(plan.total_trading_tax),
+              plan.total_ducats,
+              plan.trades(self.settings_manager.trade_limits()),
+            )).strong());
+            credit_warning(ui, plan.total_trading_tax, self.settings_manager.credit_balance());
+
+            ScrollArea::vertical().show(ui, |ui| {
+              egui::Grid::new("purchase_plan").striped(true).show(ui, |ui| {
+                ui.strong("User");
+                ui.strong("Item");
+                ui.strong("Quantity");
+                ui.strong("Platinum");
+                ui.strong("Ducats");
+                ui.strong("Credits");
+                ui.end_row();
+
+                for purchase in &plan.purchases {
+                  ui.label(&purchase.order.user.ingame_name);
+                  ui.label(purchase.order.item_name.as_deref().unwrap_or("Unknown"));
+                  ui.label(format!("{} / {}", purchase.quantity, purchase.order.quantity));
+                  ui.label(purchase.platinum.to_string());
+                  ui.label(purchase.ducats.to_string());
+                  ui.label(lib::format_credits(purchase.trading_tax));
+                  ui.end_row();
+                }
+              });
+            });
+          });
+    }
+
+    if self.show_planner {
+      egui::Window::new("Baro Planner")
+          .open(&mut self.show_planner)
+          .resizable(true)
+          .show(ctx, |ui| {
+            let settings = self.settings_manager.get_current_settings_mut();
+
+            ui.horizontal(|ui| {
+              ui.label("Ducat target:");
+              if let Ok(mut value) = settings.ducat_target().parse::<u32>()
+                  && ui.add(DragValue::new(&mut value).range(0..=MAX_DUCAT_TARGET).suffix(" ducats")).changed()
+              {
+                settings.set_ducat_target(value.to_string());
+              }
+            });
+            ui.horizontal(|ui| {
+              ui.label("Minutes per trade:");
+              if let Ok(mut value) = settings.minutes_per_trade().parse::<u32>()
+                  && ui.add(DragValue::new(&mut value).range(1..=30)).changed()
+              {
+                settings.set_minutes_per_trade(value.to_string());
+              }
+            });
+
+            let target = settings.ducat_target().parse::<u32>().unwrap_or_default().min(MAX_DUCAT_TARGET);
+            let minutes_per_trade = settings.minutes_per_trade().parse::<u32>().unwrap_or_default();
+            ui.add_enabled_ui(self.processed_orders.is_some(), |ui| {
+              if ui.button("Plan").on_disabled_hover_text("Process orders first").clicked()
+                  && let Some(processed_orders) = &self.processed_orders
+              {
+                self.visit_plan = Some(lib::plan_visit(
+                  processed_orders,
+                  target,
+                  minutes_per_trade,
+                  self.settings_manager.trade_limits(),
+                ));
+              }
+            });
+
+            ui.add_space(10.0);
+
+            let Some(visit_plan) = &self.visit_plan else {
+              return;
+            };
+
+            let plan = &visit_plan.plan;
+            ui.label(egui::RichText::new(format!(
+              "{} ducats for {} platinum and {} credits in {} trades (about {})",
+              plan.total_ducats,
+              plan.total_platinum,
+              lib::format_credits(plan.total_trading_tax),
+              visit_plan.trades,
+              lib::format_countdown(chrono::Duration::minutes(visit_plan.estimated_minutes.into())),
+            )).strong());
+            credit_warning(ui, plan.total_trading_tax, self.settings_manager.credit_balance());
+            let daily_trade_limit = self.settings_manager.daily_trade_limit() as usize;
+            if daily_trade_limit > 0 && visit_plan.trades > daily_trade_limit {
+              ui.colored_label(
+                egui::Color32::YELLOW,
+                format!(
+                  "Needs {} trades, more than your daily limit of {}",
+                  visit_plan.trades,
+                  daily_trade_limit,
+                ),
+              );
+            }
+            if !visit_plan.is_target_reached() {
+              ui.colored_label(
+                egui::Color32::YELLOW,
+                format!(
+                  "Processed orders only cover {} of {} ducats",
+                  plan.total_ducats,
+                  visit_plan.target_ducats,
+                ),
+              );
+            }
+
+            let bought_quantity = |order_id: &str| self.ledger.bought_quantity(order_id);
+            let bought_ducats = visit_plan.bought_ducats(bought_quantity);
+            ui.add(
+              egui::ProgressBar::new(visit_plan.progress(bought_quantity))
+                  .text(format!("{} / {} ducats bought", bought_ducats, visit_plan.target_ducats)),
+            );
+
+            ScrollArea::vertical().show(ui, |ui| {
+              egui::Grid::new("visit_plan").striped(true).show(ui, |ui| {
+                ui.strong("User");
+                ui.strong("Item");
+                ui.strong("Quantity");
+                ui.strong("Platinum");
+                ui.strong("Ducats");
+                ui.strong("Bought");
+                ui.end_row();
+
+                for purchase in &plan.purchases {
+                  ui.label(&purchase.order.user.ingame_name);
+                  ui.label(purchase.order.item_name.as_deref().unwrap_or("Unknown"));
+                  ui.label(format!("{} / {}", purchase.quantity, purchase.order.quantity));
+                  ui.label(purchase.platinum.to_string());
+                  ui.label(purchase.ducats.to_string());
+                  purchase_row(
+                    ui,
+                    &mut self.ledger,
+                    &mut self.settings_manager,
+                    &purchase.order,
+                    purchase.quantity,
+                    purchase.platinum,
+                    now,
+                  );
+                  ui.end_row();
+                }
+              });
+            });
+          });
+    }
+
     if self.show_credits {
       egui::Window::new("Credits")
           .open(&mut self.show_credits)
@@ -479,7 +1288,7 @@ impl eframe::App for MyApp {
       egui::Window::new("All Orders")
           .open(&mut self.show_all_orders)
           .resizable(true)
-          .scroll2([true, true])
+          .scroll([true, true])
           .show(ctx, |ui| {
             if let Some(orders) = &self.orders {
               ui.label("Fetched Orders:");
@@ -510,6 +1319,9 @@ impl eframe::App for MyApp {
                             "https://warframe.market/items/{}",
                             item_url
                           ));
+                          if ui.small_button("Details").clicked() {
+                            self.detail_item_url = Some(item_url.to_string());
+                          }
                         }
                       });
 
@@ -523,6 +1335,21 @@ impl eframe::App for MyApp {
                         ui.monospace(order.quantity.to_string());
                       });
 
+                      ui.horizontal(|ui| {
+                        ui.label("Region:");
+                        ui.monospace(&*order.region);
+                        ui.label("Platform:");
+                        ui.monospace(&*order.platform);
+                      });
+
+                      ui.horizontal(|ui| {
+                        ui.label("Updated:");
+                        ui.monospace(format!("{} ago", lib::format_age(order.age(now))));
+                        if let Some(lifecycle) = order.lifecycle {
+                          lifecycle_badge(ui, lifecycle);
+                        }
+                      });
+
                       ui.horizontal(|ui| {
                         ui.label("User:");
                         ui.monospace(&order.user.ingame_name);
@@ -549,6 +1376,361 @@ impl eframe::App for MyApp {
           });
     }
 
+    if self.show_history {
+      egui::Window::new("Price History")
+          .open(&mut self.show_history)
+          .resizable(true)
+          .show(ctx, |ui| {
+            let item_urls = self.price_history.item_urls();
+            if item_urls.is_empty() {
+              ui.label("No fetches recorded yet.");
+              return;
+            }
+
+            let selected = self.history_item_url.get_or_insert_with(|| item_urls[0].to_string());
+            egui::ComboBox::from_label("Item")
+                .selected_text(selected.as_str())
+                .show_ui(ui, |ui| {
+                  for item_url in &item_urls {
+                    ui.selectable_value(selected, item_url.to_string(), *item_url);
+                  }
+                });
+
+            let cheap_price = self.settings_manager
+                .get_current_settings()
+                .max_price_to_search()
+                .parse::<u32>()
+                .unwrap_or_default();
+            ui.label(format!(
+              "{} snapshots kept. Sellers asking at most {} platinum count as cheap.",
+              self.price_history.snapshots().len(),
+              cheap_price,
+            ));
+            ui.add_space(10.0);
+
+            let series = self.price_history.series(selected, cheap_price);
+            ScrollArea::vertical().show(ui, |ui| {
+              egui::Grid::new("price_history").striped(true).show(ui, |ui| {
+                ui.strong("Fetched");
+                ui.strong("Min price");
+                ui.strong("Cheap sellers");
+                ui.strong("Cheap quantity");
+                ui.end_row();
+
+                for point in series.iter().rev() {
+                  ui.label(point.taken_at.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M").to_string());
+                  ui.label(point.min_price.map_or("-".to_string(), |price| price.to_string()));
+                  ui.label(point.cheap_sellers.to_string());
+                  ui.label(point.cheap_quantity.to_string());
+                  ui.end_row();
+                }
+              });
+            });
+          });
+    }
+
+    if self.show_contacts {
+      egui::Window::new("Contacts")
+          .open(&mut self.show_contacts)
+          .resizable(true)
+          .show(ctx, |ui| {
+            if self.settings_manager.contacts().is_empty() {
+              ui.label("No sellers contacted yet.");
+              return;
+            }
+            ui.horizontal(|ui| {
+              ui.label("Show unanswered orders again after:");
+              let mut value = self.settings_manager.contact_expiry_hours();
+              if ui.add(DragValue::new(&mut value).range(0..=24 * 30).suffix(" hours"))
+                  .on_hover_text("Orders whispered without a reply are hidden this long. 0 hides them until cleared")
+                  .changed()
+              {
+                self.settings_manager.set_contact_expiry_hours(value);
+              }
+            });
+            if ui.button("Clear All Contacts")
+                .on_hover_text("Forget every contact so their orders show up again")
+                .clicked()
+            {
+              self.settings_manager.clear_contacts();
+              self.toasts.success("All contacts cleared");
+              return;
+            }
+            ui.add_space(10.0);
+
+            let expiry = self.settings_manager.contact_expiry();
+
+            let mut removed = None;
+            ScrollArea::vertical().show(ui, |ui| {
+              egui::Grid::new("contacts").striped(true).show(ui, |ui| {
+                ui.strong("User");
+                ui.strong("Item");
+                ui.strong("Offered");
+                ui.strong("Contacted");
+                ui.strong("State");
+                ui.strong("Hidden");
+                ui.strong("");
+                ui.end_row();
+
+                let contacts = self.settings_manager.contacts().to_vec();
+                for contact in contacts.iter().rev() {
+                  ui.label(&contact.user).on_hover_text(&contact.message);
+                  ui.horizontal(|ui| {
+                    ui.label(contact.item_name.as_deref().unwrap_or("Unknown"));
+                    if self.gone_order_ids.contains(&contact.order_id) {
+                      lifecycle_badge(ui, lib::OrderLifecycle::Gone);
+                    }
+                  });
+                  ui.label(contact.price_offered.map_or("-".to_string(), |price| format!("{} platinum", price)));
+                  ui.label(format!("{} ago", lib::format_age(now - contact.contacted_at)));
+                  contact_state_combo(ui, &mut self.settings_manager, contact, now);
+                  ui.label(match contact.eligible_again_at(expiry) {
+                    None => "Until removed".to_string(),
+                    Some(at) if at <= now => "No, shown again".to_string(),
+                    Some(at) => format!("For {}", lib::format_countdown(at - now)),
+                  });
+                  if ui.small_button("Remove").on_hover_text("Show this order again").clicked() {
+                    removed = Some(contact.order_id.clone());
+                  }
+                  ui.end_row();
+                }
+              });
+            });
+            if let Some(order_id) = removed {
+              self.settings_manager.remove_contact(&order_id);
+            }
+          });
+    }
+
+    if self.show_inventory {
+      egui::Window::new("Inventory")
+          .open(&mut self.show_inventory)
+          .resizable(true)
+          .show(ctx, |ui| {
+            let inventory = self.ledger.inventory();
+            let total_ducats: u32 = inventory.iter().map(|item| item.ducats * item.quantity).sum();
+            ui.label(egui::RichText::new(format!("Parts on hand: {} ducats", total_ducats)).strong());
+            ui.add_space(10.0);
+
+            egui::Grid::new("inventory").striped(true).show(ui, |ui| {
+              ui.strong("Item");
+              ui.strong("Quantity");
+              ui.strong("Ducats");
+              ui.strong("Convert at Baro");
+              ui.end_row();
+
+              for item in &inventory {
+                ui.label(&item.item_name);
+                ui.label(item.quantity.to_string());
+                ui.label((item.ducats * item.quantity).to_string());
+                ui.horizontal(|ui| {
+                  let quantity = self.conversion_quantities
+                      .entry(item.item_url.clone())
+                      .or_insert(item.quantity);
+                  *quantity = (*quantity).min(item.quantity);
+                  ui.add(DragValue::new(quantity).range(1..=item.quantity));
+                  if ui.button("Convert").clicked() {
+                    match self.ledger.record_conversion(&item.item_url, *quantity, now) {
+                      Ok(()) => self.toasts.success(format!(
+                        "Converted {} {} for {} ducats",
+                        quantity,
+                        item.item_name,
+                        item.ducats * *quantity,
+                      )),
+                      Err(err) => self.toasts.error(err),
+                    };
+                  }
+                });
+                ui.end_row();
+              }
+            });
+
+            ui.add_space(10.0);
+            ui.collapsing(format!("Purchases ({})", self.ledger.purchases().len()), |ui| {
+              let mut removed = None;
+              ScrollArea::vertical().show(ui, |ui| {
+                egui::Grid::new("purchases").striped(true).show(ui, |ui| {
+                  ui.strong("Bought");
+                  ui.strong("User");
+                  ui.strong("Item");
+                  ui.strong("Quantity");
+                  ui.strong("Platinum paid");
+                  ui.strong("Ducats");
+                  ui.strong("");
+                  ui.end_row();
+
+                  for index in (0..self.ledger.purchases().len()).rev() {
+                    let purchase = self.ledger.purchases()[index].clone();
+                    ui.label(purchase.bought_at.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M").to_string());
+                    ui.label(&purchase.user);
+                    ui.label(&purchase.item_name);
+                    ui.label(purchase.quantity.to_string());
+                    let mut platinum = purchase.platinum;
+                    if ui.add(DragValue::new(&mut platinum)).changed() {
+                      self.ledger.set_purchase_platinum(index, platinum);
+                    }
+                    ui.label(purchase.total_ducats().to_string());
+                    if ui.small_button("Remove").clicked() {
+                      removed = Some(index);
+                    }
+                    ui.end_row();
+                  }
+                });
+              });
+              if let Some(index) = removed {
+                self.ledger.remove_purchase(index);
+              }
+            });
+            ui.collapsing(format!("Baro conversions ({})", self.ledger.conversions().len()), |ui| {
+              for conversion in self.ledger.conversions().iter().rev() {
+                ui.label(format!(
+                  "{}: {} x{} for {} ducats",
+                  conversion.converted_at.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M"),
+                  conversion.item_url,
+                  conversion.quantity,
+                  conversion.ducats,
+                ));
+              }
+            });
+          });
+    }
+
+    if self.show_reports {
+      egui::Window::new("Reports")
+          .open(&mut self.show_reports)
+          .resizable(true)
+          .show(ctx, |ui| {
+            egui::ComboBox::from_label("Group purchases")
+                .selected_text(self.report_period.label())
+                .show_ui(ui, |ui| {
+                  for period in lib::reports::ReportPeriod::ALL {
+                    ui.selectable_value(&mut self.report_period, period, period.label());
+                  }
+                });
+
+            let baro_arrival = self.void_trader.as_ref().map(|trader| trader.activation);
+            if self.report_period == lib::reports::ReportPeriod::BaroVisit && baro_arrival.is_none() {
+              ui.label("Enable the Baro Ki'Teer countdown in Global Settings to group by visit.");
+              return;
+            }
+            let reports = lib::reports::build_reports(self.ledger.purchases(), self.report_period, baro_arrival);
+            if reports.is_empty() {
+              ui.label("No purchases recorded yet.");
+              return;
+            }
+
+            ui.horizontal(|ui| {
+              for format in [lib::reports::ExportFormat::Csv, lib::reports::ExportFormat::Json] {
+                if ui.button(format!("Export {}", format.extension().to_uppercase())).clicked() {
+                  match lib::reports::export_reports(&reports, self.report_period, format) {
+                    Ok(path) => self.toasts.success(format!("Exported to {}", path.display())),
+                    Err(err) => self.toasts.error(format!("Error exporting reports: {}", err)),
+                  };
+                }
+              }
+            });
+            ui.add_space(10.0);
+
+            ScrollArea::vertical().show(ui, |ui| {
+              egui::Grid::new("reports").striped(true).show(ui, |ui| {
+                ui.strong("Period");
+                ui.strong("Purchases");
+                ui.strong("Platinum");
+                ui.strong("Ducats");
+                ui.strong("Ducats/plat");
+                ui.strong("Best items");
+                ui.strong("Top sellers");
+                ui.end_row();
+
+                for report in &reports {
+                  ui.label(report.period.to_string());
+                  ui.label(report.purchases.to_string());
+                  ui.label(report.platinum.to_string());
+                  ui.label(report.ducats.to_string());
+                  ui.label(format!("{:.1}", report.ducats_per_platinum));
+                  let best_items: Vec<String> = report.best_items
+                      .iter()
+                      .map(|item| format!("{} ({:.1})", item.item_name, item.ducats_per_platinum))
+                      .collect();
+                  ui.label(best_items.join(", "));
+                  let top_sellers: Vec<String> = report.top_sellers
+                      .iter()
+                      .map(|(user, count)| format!("{} ({})", user, count))
+                      .collect();
+                  ui.label(top_sellers.join(", "));
+                  ui.end_row();
+                }
+              });
+            });
+          });
+    }
+
+    if let Some(item_url) = self.detail_item_url.clone() {
+      let mut is_open = true;
+      egui::Window::new("Item Details")
+          .open(&mut is_open)
+          .resizable(true)
+          .show(ctx, |ui| {
+            let Some(item) = self.items.get(&item_url) else {
+              ui.label(format!("No details available for {}.", item_url));
+              ui.label("Fetch orders for this item to load its details.");
+              return;
+            };
+
+            egui::ComboBox::from_label("Language")
+                .selected_text(&self.detail_language)
+                .show_ui(ui, |ui| {
+                  for language in lib::LANGUAGES {
+                    ui.selectable_value(&mut self.detail_language, language.to_string(), language);
+                  }
+                });
+
+            ui.add_space(10.0);
+
+            let Some(locale) = item.locale(&self.detail_language) else {
+              ui.label("No localized info for this item.");
+              return;
+            };
+
+            ui.heading(&locale.item_name);
+            if !locale.description.is_empty() {
+              ui.label(&locale.description);
+            }
+            if let Some(wiki_link) = &locale.wiki_link {
+              ui.hyperlink_to("Wiki", wiki_link);
+            }
+
+            ui.add_space(10.0);
+
+            ui.horizontal(|ui| {
+              ui.label("Ducats:");
+              ui.monospace(item.ducats.map_or("-".to_string(), |d| d.to_string()));
+            });
+            ui.horizontal(|ui| {
+              ui.label("Rarity:");
+              ui.monospace(item.rarity.as_deref().unwrap_or("-"));
+            });
+
+            ui.add_space(10.0);
+            ui.label(egui::RichText::new("Drop Sources").strong());
+            if locale.drop.is_empty() {
+              ui.label("No drop sources listed.");
+            }
+            for drop in &locale.drop {
+              match &drop.link {
+                Some(link) => {
+                  ui.hyperlink_to(&drop.name, link);
+                }
+                None => {
+                  ui.label(&drop.name);
+                }
+              }
+            }
+          });
+      if !is_open {
+        self.detail_item_url = None;
+      }
+    }
 
     if self.show_delete_presets_confirmation {
       let modal = egui::Modal::new(egui::Id::new("delete_presets_confirmation"))
@@ -598,6 +1780,201 @@ impl eframe::App for MyApp {
   }
 }
 
+/// Describes an order's ducat value and ducats per platinum at the offered price.
+fn ducats_summary(order: &lib::Order) -> String {
+  match (order.ducats, order.sum_ducats, order.ducats_per_platinum) {
+    (Some(ducats), Some(sum_ducats), Some(ducats_per_platinum)) => format!(
+      "{} ducats each, {} ducats for {} platinum ({:.1} ducats/plat), {} credits tax",
+      ducats,
+      sum_ducats,
+      order.sum_to_offer.unwrap_or_default(),
+      ducats_per_platinum,
+      lib::format_credits(order.sum_trading_tax.unwrap_or_default()),
+    ),
+    _ => "Unknown ducat value".to_string(),
+  }
+}
+
+/// Warns when trading tax is more than the configured credit balance.
+fn credit_warning(ui: &mut egui::Ui, trading_tax: u64, credit_balance: u64) {
+  if credit_balance > 0 && trading_tax > credit_balance {
+    ui.colored_label(
+      egui::Color32::YELLOW,
+      format!(
+        "Trading tax of {} credits is more than your balance of {} credits",
+        lib::format_credits(trading_tax),
+        lib::format_credits(credit_balance),
+      ),
+    );
+  }
+}
+
+/// Shows an order's age, lifecycle badge and a details button.
+/// Returns true if the details button was clicked.
+fn order_status_row(ui: &mut egui::Ui, order: &lib::Order, now: chrono::DateTime<chrono::Utc>) -> bool {
+  ui.horizontal(|ui| {
+    ui.label(format!("Updated {} ago", lib::format_age(order.age(now))));
+    if let Some(lifecycle) = order.lifecycle {
+      lifecycle_badge(ui, lifecycle);
+    }
+    if let Some(deal_score) = &order.deal_score {
+      let breakdown: Vec<String> = deal_score.contributions
+          .iter()
+          .map(|(factor, contribution)| format!("{}: +{:.2}", factor.label(), contribution))
+          .collect();
+      ui.label(format!("Score {:.1}", deal_score.total)).on_hover_text(breakdown.join("\n"));
+    }
+    if order.is_favourite == Some(true) {
+      ui.label(egui::RichText::new("★ Favourite").color(egui::Color32::GOLD).strong())
+          .on_hover_text("Favourite sellers are ranked first");
+    }
+    if !order.anomalies.is_empty() {
+      let reasons: Vec<String> = order.anomalies
+          .iter()
+          .map(|a| format!("{}: {}", a.label(), a.description()))
+          .collect();
+      ui.label(egui::RichText::new("⚠ Suspicious").color(egui::Color32::ORANGE).strong())
+          .on_hover_text(reasons.join("\n"));
+    }
+    ui.small_button("Details").clicked()
+  }).inner
+}
+
+/// Shows the negotiation state of a contacted order so it can be moved along.
+fn contact_state_row(
+  ui: &mut egui::Ui,
+  settings_manager: &mut lib::settings::SettingsManager,
+  order_id: &str,
+  now: chrono::DateTime<chrono::Utc>,
+) {
+  let Some(contact) = settings_manager.contact(order_id).cloned() else {
+    return;
+  };
+  ui.horizontal(|ui| {
+    ui.label(format!("Contacted {} ago:", lib::format_age(now - contact.contacted_at)));
+    contact_state_combo(ui, settings_manager, &contact, now);
+  });
+}
+
+/// Lists the trades a seller's orders are split into.
+fn trades_list(ui: &mut egui::Ui, seller: &str, trades: &[lib::trades::Trade]) {
+  egui::CollapsingHeader::new(format!("{} trades", trades.len())).id_salt(("trades", seller)).show(ui, |ui| {
+    for (i, trade) in trades.iter().enumerate() {
+      let items: Vec<String> = trade
+          .stacks
+          .iter()
+          .map(|stack| format!("{} x{}", stack.item_name, stack.quantity))
+          .collect();
+      ui.label(format!(
+        "Trade {}: {} items for {} platinum ({})",
+        i + 1,
+        trade.quantity(),
+        trade.platinum,
+        items.join(", "),
+      ));
+    }
+  });
+}
+
+/// A seller's card in the processed list, with its whispers and trades worked
+/// out once per processing run rather than every frame.
+struct SellerCard {
+  /// The seller's orders, as a range of the processed orders.
+  orders: std::ops::Range<usize>,
+  messages: Vec<String>,
+  trades: Vec<lib::trades::Trade>,
+  /// Height including the gap below, from when the card was last shown.
+  height: f32,
+}
+
+/// Groups processed orders into one card per seller.
+fn seller_cards(
+  processed_orders: &[lib::Order],
+  pricing: &dyn lib::PricingStrategy,
+  trade_limits: lib::TradeLimits,
+) -> Vec<SellerCard> {
+  let mut cards = Vec::new();
+  let mut start = 0;
+  for seller_orders in processed_orders.chunk_by(|a, b| a.user.ingame_name == b.user.ingame_name) {
+    let messages = if let [order] = seller_orders {
+      vec![lib::generate_message(order, pricing, trade_limits)]
+    } else {
+      lib::generate_combined_message(seller_orders, pricing, trade_limits)
+    };
+    cards.push(SellerCard {
+      orders: start..start + seller_orders.len(),
+      trades: lib::split_into_trades(seller_orders, trade_limits),
+      // Rough guess until the card is shown: whisper buttons and a few rows per order
+      height: 110.0 * messages.len() as f32 + 90.0 * seller_orders.len() as f32,
+      messages,
+    });
+    start += seller_orders.len();
+  }
+  cards
+}
+
+/// Records buying `quantity` items of an order for `platinum` in total, or
+/// shows that they were already bought. Items bought earlier count toward
+/// `quantity`, and only the rest is recorded.
+#[allow(clippy::too_many_arguments)]
+fn purchase_row(
+  ui: &mut egui::Ui,
+  ledger: &mut lib::ledger::Ledger,
+  settings_manager: &mut lib::settings::SettingsManager,
+  order: &lib::Order,
+  quantity: u32,
+  platinum: u32,
+  now: chrono::DateTime<chrono::Utc>,
+) {
+  let bought = ledger.bought_quantity(&order.id);
+  if bought >= quantity {
+    ui.label(egui::RichText::new("Bought").color(egui::Color32::LIGHT_GREEN));
+    return;
+  }
+  let remaining = quantity - bought;
+  let hover_text = if bought > 0 {
+    format!("{} of {} items already bought. Add the other {} to the purchase ledger", bought, quantity, remaining)
+  } else {
+    format!("Add {} items to the purchase ledger for {} platinum", quantity, platinum)
+  };
+  if ui.button("Mark as bought").on_hover_text(hover_text).clicked() {
+    let remaining_platinum = (platinum as u64 * remaining as u64 / quantity as u64) as u32;
+    ledger.record_purchase(order, remaining, remaining_platinum, now);
+    settings_manager.set_contact_state(&order.id, lib::contacts::ContactState::Traded, now);
+  }
+}
+
+fn contact_state_combo(
+  ui: &mut egui::Ui,
+  settings_manager: &mut lib::settings::SettingsManager,
+  contact: &lib::contacts::ContactRecord,
+  now: chrono::DateTime<chrono::Utc>,
+) {
+  let mut state = contact.state;
+  egui::ComboBox::from_id_salt(("contact_state", &contact.order_id))
+      .selected_text(state.label())
+      .show_ui(ui, |ui| {
+        for option in lib::contacts::ContactState::ALL {
+          ui.selectable_value(&mut state, option, option.label());
+        }
+      });
+  if state != contact.state {
+    settings_manager.set_contact_state(&contact.order_id, state, now);
+  }
+}
+
+/// Shows a colored badge for an order's lifecycle state since the previous fetch.
+fn lifecycle_badge(ui: &mut egui::Ui, lifecycle: lib::OrderLifecycle) {
+  let color = match lifecycle {
+    lib::OrderLifecycle::New => egui::Color32::LIGHT_GREEN,
+    lib::OrderLifecycle::PriceDropped => egui::Color32::LIGHT_BLUE,
+    lib::OrderLifecycle::Gone => egui::Color32::GRAY,
+    lib::OrderLifecycle::Unchanged => return,
+    _ => egui::Color32::YELLOW,
+  };
+  ui.label(egui::RichText::new(lifecycle.label()).color(color).strong());
+}
+
 fn main() -> eframe::Result {
   if std::env::var("RUST_LOG").is_err() {
     unsafe {